@@ -0,0 +1,97 @@
+use core::time::Duration;
+
+/// Platform services used by the drivers.
+///
+/// Implement it for a type of your own and register it with [`set_impl!`](crate::set_impl).
+pub trait Kernel {
+    /// Monotonic time since boot.
+    fn now() -> Duration;
+
+    /// Block for at least `duration`, busy waiting on [`Kernel::now`] by default.
+    fn sleep(duration: Duration) {
+        let deadline = Self::now() + duration;
+        while Self::now() < deadline {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Give the CPU to other work for a moment, a spin hint by default.
+    fn yield_now() {
+        core::hint::spin_loop();
+    }
+
+    /// Feed the platform watchdog during long driver operations, no-op by default.
+    fn feed_watchdog() {}
+
+    /// Current reference clock of the UART mapped at `base`, `None` by default
+    /// when the platform has no clock tree to ask.
+    fn uart_clock_hz(base: usize) -> Option<u32> {
+        let _ = base;
+        None
+    }
+}
+
+unsafe extern "Rust" {
+    safe fn __my_driver_now() -> Duration;
+    safe fn __my_driver_sleep(duration: Duration);
+    safe fn __my_driver_yield_now();
+    safe fn __my_driver_feed_watchdog();
+    safe fn __my_driver_uart_clock_hz(base: usize) -> Option<u32>;
+}
+
+pub(crate) fn now() -> Duration {
+    __my_driver_now()
+}
+
+pub(crate) fn sleep(duration: Duration) {
+    __my_driver_sleep(duration)
+}
+
+pub(crate) fn yield_now() {
+    __my_driver_yield_now()
+}
+
+pub(crate) fn feed_watchdog() {
+    __my_driver_feed_watchdog()
+}
+
+pub(crate) fn uart_clock_hz(base: usize) -> Option<u32> {
+    __my_driver_uart_clock_hz(base)
+}
+
+/// Register the [`Kernel`] implementation used by the drivers.
+///
+/// ```ignore
+/// struct KernelImpl;
+/// impl my_driver::kernel::Kernel for KernelImpl {
+///     fn now() -> core::time::Duration {
+///         todo!()
+///     }
+/// }
+/// my_driver::set_impl!(KernelImpl);
+/// ```
+#[macro_export]
+macro_rules! set_impl {
+    ($t:ty) => {
+        #[unsafe(no_mangle)]
+        fn __my_driver_now() -> core::time::Duration {
+            <$t as $crate::kernel::Kernel>::now()
+        }
+        #[unsafe(no_mangle)]
+        fn __my_driver_sleep(duration: core::time::Duration) {
+            <$t as $crate::kernel::Kernel>::sleep(duration)
+        }
+        #[unsafe(no_mangle)]
+        fn __my_driver_yield_now() {
+            <$t as $crate::kernel::Kernel>::yield_now()
+        }
+        #[unsafe(no_mangle)]
+        fn __my_driver_feed_watchdog() {
+            <$t as $crate::kernel::Kernel>::feed_watchdog()
+        }
+        #[unsafe(no_mangle)]
+        fn __my_driver_uart_clock_hz(base: usize) -> Option<u32> {
+            <$t as $crate::kernel::Kernel>::uart_clock_hz(base)
+        }
+    };
+}
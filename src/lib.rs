@@ -1,6 +1,35 @@
+//! Drivers for the Phytium UART and QSPI controllers.
+//!
+//! The drivers get time, sleeping and the watchdog from the platform through
+//! [`kernel::Kernel`]. Exactly one implementation has to be registered with
+//! [`set_impl!`] somewhere in the final binary, otherwise linking fails on the
+//! missing `__my_driver_*` symbols:
+//!
+//! ```ignore
+//! struct KernelImpl;
+//! impl my_driver::kernel::Kernel for KernelImpl {
+//!     fn now() -> core::time::Duration {
+//!         todo!()
+//!     }
+//! }
+//! my_driver::set_impl!(KernelImpl);
+//! ```
 #![no_std]
 
 extern crate alloc;
+pub mod kernel;
+pub mod misc;
 pub mod mutex;
 pub mod qspi;
 pub mod uart;
+
+/// Best effort quiesce of the hardware, call it first thing in the panic handler.
+///
+/// Masks the interrupts of the console set with
+/// [`PhytiumUart::set_console`](uart::pl011::PhytiumUart::set_console) and returns
+/// a fresh polled handle to it, so the panic message can go out with
+/// [`PhytiumUart::put_byte_poll`](uart::pl011::PhytiumUart::put_byte_poll) even if
+/// the panicking code owns the regular one. None of the drivers here do DMA.
+pub fn panic_quiesce() -> Option<uart::pl011::PhytiumUart> {
+    uart::pl011::quiesce_console()
+}
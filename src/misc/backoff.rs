@@ -0,0 +1,79 @@
+use core::time::Duration;
+
+use crate::kernel;
+
+/// Steps spent spinning, the last one spins `1 << SPIN_LIMIT` times.
+const SPIN_LIMIT: u32 = 6;
+/// Steps after which yielding gives way to sleeping.
+const YIELD_LIMIT: u32 = 10;
+
+/// Escalating wait for contended locks and hardware polls.
+///
+/// Spin hints first, cheap when the wait is short, then yields through
+/// [`Kernel::yield_now`](crate::kernel::Kernel::yield_now), then sleeps
+/// doubling up to a bound, so long waits don't burn a CPU, which is
+/// especially costly under emulation.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    step: u32,
+    first_sleep: Duration,
+    sleep: Duration,
+    max_sleep: Duration,
+}
+
+impl Backoff {
+    pub const fn new() -> Self {
+        Self::with_sleep(Duration::from_micros(1), Duration::from_millis(1))
+    }
+
+    /// Sleep `first` once spinning and yielding are done, then double up to `max`.
+    pub const fn with_sleep(first: Duration, max: Duration) -> Self {
+        Self {
+            step: 0,
+            first_sleep: first,
+            sleep: first,
+            max_sleep: max,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.step = 0;
+        self.sleep = self.first_sleep;
+    }
+
+    /// Spin stage only, for callers that must not call into the kernel.
+    pub fn spin(&mut self) {
+        for _ in 0..1u32 << self.step.min(SPIN_LIMIT) {
+            core::hint::spin_loop();
+        }
+        if self.step <= SPIN_LIMIT {
+            self.step += 1;
+        }
+    }
+
+    /// Wait a little longer than last time.
+    pub fn snooze(&mut self) {
+        if self.step <= SPIN_LIMIT {
+            self.spin();
+            return;
+        }
+        if self.step <= YIELD_LIMIT {
+            kernel::yield_now();
+        } else {
+            kernel::sleep(self.sleep);
+            self.sleep = (self.sleep * 2).min(self.max_sleep);
+        }
+        self.step = self.step.saturating_add(1);
+    }
+
+    /// Whether the spin and yield stages are over.
+    pub fn is_sleeping(&self) -> bool {
+        self.step > YIELD_LIMIT
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
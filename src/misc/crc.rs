@@ -0,0 +1,167 @@
+//! Table driven CRCs: CRC-32 (IEEE 802.3), CRC-32C (Castagnoli) and
+//! CRC-16/CCITT-FALSE.
+
+const fn reflected_table(poly: u32) -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ poly
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+const fn table16(poly: u16) -> [u16; 256] {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ poly
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+static CRC32_TABLE: [u32; 256] = reflected_table(0xedb8_8320);
+static CRC32C_TABLE: [u32; 256] = reflected_table(0x82f6_3b78);
+static CRC16_CCITT_TABLE: [u16; 256] = table16(0x1021);
+
+/// Streaming reflected 32 bit CRC.
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32 {
+    table: &'static [u32; 256],
+    state: u32,
+}
+
+impl Crc32 {
+    /// CRC-32 as used by Ethernet, zlib and PNG.
+    pub const fn new() -> Self {
+        Self {
+            table: &CRC32_TABLE,
+            state: !0,
+        }
+    }
+
+    /// CRC-32C, as used by iSCSI and ext4.
+    pub const fn castagnoli() -> Self {
+        Self {
+            table: &CRC32C_TABLE,
+            state: !0,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &b in data {
+            self.state = self.table[((self.state ^ b as u32) & 0xff) as usize] ^ (self.state >> 8);
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Streaming CRC-16/CCITT-FALSE (poly 0x1021, init 0xffff).
+#[derive(Debug, Clone, Copy)]
+pub struct Crc16 {
+    state: u16,
+}
+
+impl Crc16 {
+    pub const fn new() -> Self {
+        Self { state: 0xffff }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &b in data {
+            self.state =
+                CRC16_CCITT_TABLE[((self.state >> 8) as u8 ^ b) as usize] ^ (self.state << 8);
+        }
+    }
+
+    pub fn finish(&self) -> u16 {
+        self.state
+    }
+}
+
+impl Default for Crc16 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finish()
+}
+
+pub fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = Crc32::castagnoli();
+    crc.update(data);
+    crc.finish()
+}
+
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc = Crc16::new();
+    crc.update(data);
+    crc.finish()
+}
+
+/// Ethernet FCS of `frame` (destination address to end of payload), in wire order.
+pub fn ethernet_fcs(frame: &[u8]) -> [u8; 4] {
+    crc32(frame).to_le_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_check_values() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29b1);
+
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finish(), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_ethernet_fcs() {
+        let mut frame = [0u8; 64];
+        for (i, b) in frame.iter_mut().enumerate().take(60) {
+            *b = i as u8;
+        }
+        let fcs = ethernet_fcs(&frame[..60]);
+        frame[60..].copy_from_slice(&fcs);
+        // a frame followed by its FCS leaves the CRC-32 residue
+        assert_eq!(!crc32(&frame), 0xdebb_20e3);
+    }
+}
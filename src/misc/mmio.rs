@@ -0,0 +1,114 @@
+use core::{mem::size_of, ptr::NonNull};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Register widths a [`RegBlock`] can access.
+pub trait RegValue: sealed::Sealed + Copy {}
+
+macro_rules! reg_value {
+    ($($t:ty),*) => {
+        $(
+            impl sealed::Sealed for $t {}
+            impl RegValue for $t {}
+        )*
+    };
+}
+reg_value!(u8, u16, u32, u64);
+
+/// Window of device registers with volatile accesses at byte offsets.
+///
+/// For registers without a `register_structs!` map. Every offset is checked
+/// against the window, so a bad constant panics instead of poking a
+/// neighbouring device.
+#[derive(Debug, Clone, Copy)]
+pub struct RegBlock {
+    base: NonNull<u8>,
+    len: usize,
+}
+
+impl RegBlock {
+    /// # Safety
+    ///
+    /// `base` must map `len` bytes of device memory for as long as the block
+    /// and its sub blocks are used, aligned for the widest access made.
+    pub const unsafe fn new(base: NonNull<u8>, len: usize) -> Self {
+        Self { base, len }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The `len` bytes at `offset`, e.g. the registers of one queue.
+    pub fn sub(&self, offset: usize, len: usize) -> Self {
+        assert!(
+            offset.checked_add(len).is_some_and(|end| end <= self.len),
+            "mmio: sub block {offset:#x}+{len:#x} outside of {:#x} byte window",
+            self.len
+        );
+        Self {
+            base: unsafe { self.base.add(offset) },
+            len,
+        }
+    }
+
+    pub fn read<T: RegValue>(&self, offset: usize) -> T {
+        unsafe { self.ptr::<T>(offset).read_volatile() }
+    }
+
+    pub fn write<T: RegValue>(&self, offset: usize, value: T) {
+        unsafe { self.ptr::<T>(offset).write_volatile(value) }
+    }
+
+    pub fn modify<T: RegValue>(&self, offset: usize, f: impl FnOnce(T) -> T) {
+        self.write(offset, f(self.read(offset)));
+    }
+
+    fn ptr<T: RegValue>(&self, offset: usize) -> NonNull<T> {
+        assert!(
+            offset
+                .checked_add(size_of::<T>())
+                .is_some_and(|end| end <= self.len),
+            "mmio: {}-byte access at {offset:#x} outside of {:#x} byte window",
+            size_of::<T>(),
+            self.len
+        );
+        assert!(
+            offset.is_multiple_of(size_of::<T>()),
+            "mmio: unaligned {}-byte access at {offset:#x}",
+            size_of::<T>()
+        );
+        unsafe { self.base.add(offset).cast() }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reg_block() {
+        let mut mem = [0u32; 4];
+        let regs = unsafe { RegBlock::new(NonNull::from(&mut mem).cast(), 16) };
+        regs.write(4, 0x1234_5678u32);
+        regs.modify::<u32>(4, |v| v | 1);
+        assert_eq!(regs.read::<u32>(4), 0x1234_5679);
+        let queue = regs.sub(8, 8);
+        queue.write(4, 7u32);
+        assert_eq!(regs.read::<u32>(12), 7);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_reg_block_out_of_window() {
+        let mut mem = [0u32; 4];
+        let regs = unsafe { RegBlock::new(NonNull::from(&mut mem).cast(), 16) };
+        regs.sub(8, 8).read::<u32>(8);
+    }
+}
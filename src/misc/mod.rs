@@ -0,0 +1,7 @@
+pub mod backoff;
+pub mod crc;
+pub mod mmio;
+pub mod poll;
+pub mod timer;
+pub mod units;
+pub mod watchdog;
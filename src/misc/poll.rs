@@ -0,0 +1,48 @@
+use core::time::Duration;
+
+use crate::{kernel, misc::backoff::Backoff};
+
+/// A hardware poll loop that did not see its condition in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollTimeout;
+
+/// Bound and pacing of a hardware poll loop, so an absent or hung device
+/// turns into a [`PollTimeout`] instead of a silent spin forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollPolicy {
+    /// Give up after this long.
+    pub timeout: Duration,
+    /// First sleep once spinning and yielding did not help, see [`Backoff`].
+    pub interval: Duration,
+    /// Upper bound of the sleep between two reads.
+    pub max_interval: Duration,
+}
+
+impl PollPolicy {
+    pub const DEFAULT: Self = Self {
+        timeout: Duration::from_secs(1),
+        interval: Duration::from_micros(1),
+        max_interval: Duration::from_millis(1),
+    };
+
+    /// Poll `cond` until it returns `true`.
+    pub fn wait(&self, mut cond: impl FnMut() -> bool) -> Result<(), PollTimeout> {
+        let deadline = kernel::now() + self.timeout;
+        let mut backoff = Backoff::with_sleep(self.interval, self.max_interval);
+        loop {
+            if cond() {
+                return Ok(());
+            }
+            if kernel::now() >= deadline {
+                return Err(PollTimeout);
+            }
+            backoff.snooze();
+        }
+    }
+}
+
+impl Default for PollPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
@@ -0,0 +1,136 @@
+use alloc::vec::Vec;
+use core::{
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use crate::{kernel, mutex::Mutex};
+
+/// Deadline, registration id and waker of every pending [`Sleep`].
+static TIMERS: Mutex<Vec<(Duration, u64, Waker)>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Wake the expired [`Sleep`]s, call it from the platform timer interrupt.
+///
+/// Without it a [`Sleep`] only completes when it is polled again for another
+/// reason, which is fine for busy polling executors like `spin_on`. Either way
+/// a [`Sleep`] drops its registration once it completes or is dropped.
+pub fn on_tick() {
+    // the interrupted code may hold the lock, catch up on the next tick
+    let Some(mut timers) = TIMERS.try_lock() else {
+        return;
+    };
+    let now = kernel::now();
+    timers.retain(|(deadline, _, waker)| {
+        if *deadline <= now {
+            waker.wake_by_ref();
+            false
+        } else {
+            true
+        }
+    });
+}
+
+/// Earliest pending deadline, for platforms programming a one-shot timer.
+///
+/// Safe to call from the timer interrupt like [`on_tick`]: if the interrupted
+/// code holds the lock it returns `None` too, so keep a fallback tick armed
+/// rather than treating `None` as "nothing pending".
+pub fn next_deadline() -> Option<Duration> {
+    let timers = TIMERS.try_lock()?;
+    timers.iter().map(|(deadline, ..)| *deadline).min()
+}
+
+/// Future completing once [`Kernel::now`](crate::kernel::Kernel::now) passes a deadline.
+#[derive(Debug)]
+pub struct Sleep {
+    deadline: Duration,
+    /// key of the entry in `TIMERS`, 0 if never registered
+    id: u64,
+}
+
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(kernel::now() + duration)
+}
+
+pub fn sleep_until(deadline: Duration) -> Sleep {
+    Sleep { deadline, id: 0 }
+}
+
+impl Sleep {
+    pub fn deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    fn unregister(&mut self) {
+        if self.id != 0 {
+            let id = core::mem::take(&mut self.id);
+            TIMERS.lock().retain(|(_, entry, _)| *entry != id);
+        }
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if kernel::now() >= this.deadline {
+            this.unregister();
+            return Poll::Ready(());
+        }
+        let mut timers = TIMERS.lock();
+        match timers.iter_mut().find(|(_, id, _)| *id == this.id) {
+            Some((_, _, waker)) => {
+                if !waker.will_wake(cx.waker()) {
+                    *waker = cx.waker().clone();
+                }
+            }
+            None => {
+                // also after `on_tick` dropped an entry whose deadline passed
+                // between its check and ours
+                this.id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+                timers.push((this.deadline, this.id, cx.waker().clone()));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+/// Error of a [`Timeout`] whose deadline passed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed;
+
+/// Future resolving to `Err(Elapsed)` if the inner future is not done in time.
+#[derive(Debug)]
+pub struct Timeout<F> {
+    future: F,
+    sleep: Sleep,
+}
+
+pub fn timeout<F: Future>(duration: Duration, future: F) -> Timeout<F> {
+    Timeout {
+        future,
+        sleep: sleep(duration),
+    }
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // `future` is never moved out of the pinned `Timeout`
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        if let Poll::Ready(v) = future.poll(cx) {
+            return Poll::Ready(Ok(v));
+        }
+        Pin::new(&mut this.sleep).poll(cx).map(|_| Err(Elapsed))
+    }
+}
@@ -0,0 +1,53 @@
+//! Conversions between register units and [`Duration`].
+
+use core::time::Duration;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// Length of `bits` bit periods at `baud`, rounded up to the next nanosecond.
+///
+/// Saturates to [`Duration::MAX`] at a baud rate of 0.
+pub const fn bit_times(bits: u32, baud: u32) -> Duration {
+    if baud == 0 {
+        return Duration::MAX;
+    }
+    let nanos = (bits as u128 * NANOS_PER_SEC).div_ceil(baud as u128);
+    Duration::from_nanos(nanos as u64)
+}
+
+/// Baud rate at which `bits` bit periods take `elapsed`.
+pub fn baud_from(bits: u32, elapsed: Duration) -> u32 {
+    let nanos = elapsed.as_nanos().max(1);
+    (bits as u128 * NANOS_PER_SEC / nanos).min(u32::MAX as u128) as u32
+}
+
+/// Number of `tick` long timer ticks covering `duration`, rounded up and
+/// saturating, for timeout fields counted in fixed ticks.
+pub fn to_ticks(duration: Duration, tick: Duration) -> u32 {
+    let tick = tick.as_nanos().max(1);
+    duration.as_nanos().div_ceil(tick).min(u32::MAX as u128) as u32
+}
+
+/// Length of `ticks` timer ticks, saturating at [`Duration::MAX`].
+pub fn from_ticks(ticks: u32, tick: Duration) -> Duration {
+    tick.checked_mul(ticks).unwrap_or(Duration::MAX)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_units() {
+        assert_eq!(bit_times(10, 115_200), Duration::from_nanos(86_806));
+        assert_eq!(bit_times(1, 1_000_000), Duration::from_micros(1));
+        assert_eq!(bit_times(10, 0), Duration::MAX);
+        assert_eq!(baud_from(70, Duration::from_nanos(607_638)), 115_200);
+        assert_eq!(baud_from(8, Duration::ZERO), u32::MAX);
+
+        let tick = Duration::from_micros(2);
+        assert_eq!(to_ticks(Duration::from_micros(5), tick), 3);
+        assert_eq!(to_ticks(Duration::from_secs(u64::MAX), tick), u32::MAX);
+        assert_eq!(from_ticks(3, tick), Duration::from_micros(6));
+        assert_eq!(from_ticks(u32::MAX, Duration::MAX), Duration::MAX);
+    }
+}
@@ -0,0 +1,49 @@
+use core::time::Duration;
+
+use crate::kernel;
+
+/// Feeding period of [`Watchdog::new`].
+pub const DEFAULT_FEED_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Feed the platform watchdog through [`Kernel::feed_watchdog`](crate::kernel::Kernel::feed_watchdog).
+pub fn feed() {
+    kernel::feed_watchdog()
+}
+
+/// Keeps the platform watchdog fed from inside a long running loop.
+///
+/// [`Watchdog::poll`] is cheap enough to call on every iteration, the
+/// watchdog is only fed once per interval.
+#[derive(Debug)]
+pub struct Watchdog {
+    interval: Duration,
+    last: Duration,
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        Self::with_interval(DEFAULT_FEED_INTERVAL)
+    }
+
+    pub fn with_interval(interval: Duration) -> Self {
+        feed();
+        Self {
+            interval,
+            last: kernel::now(),
+        }
+    }
+
+    pub fn poll(&mut self) {
+        let now = kernel::now();
+        if now - self.last >= self.interval {
+            feed();
+            self.last = now;
+        }
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
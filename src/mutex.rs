@@ -3,6 +3,8 @@ use core::{
     ops::{Deref, DerefMut},
     sync::atomic::AtomicBool,
 };
+
+use crate::misc::backoff::Backoff;
 pub struct Mutex<T> {
     inner: AtomicBool,
     data: UnsafeCell<T>,
@@ -19,9 +21,25 @@ impl<T> Mutex<T> {
         }
     }
     pub fn lock(&self) -> MutexGuard<'_, T> {
-        while self.inner.swap(true, core::sync::atomic::Ordering::Acquire) {}
+        // spin only: the lock may be taken in irq, where the kernel can not yield
+        let mut backoff = Backoff::new();
+        while self.inner.swap(true, core::sync::atomic::Ordering::Acquire) {
+            backoff.spin();
+        }
         MutexGuard { mutex: self }
     }
+    /// Like [`Self::lock`] but gives up instead of spinning, usable from irq.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.inner
+            .compare_exchange(
+                false,
+                true,
+                core::sync::atomic::Ordering::Acquire,
+                core::sync::atomic::Ordering::Relaxed,
+            )
+            .ok()
+            .map(|_| MutexGuard { mutex: self })
+    }
     pub fn unlock(&self) {
         self.inner
             .store(false, core::sync::atomic::Ordering::Release);
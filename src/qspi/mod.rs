@@ -1 +1,60 @@
+use core::time::Duration;
+
 pub mod phytium;
+
+/// Time from the end of a deep power-down command until the part is asleep (tDP).
+pub const DEEP_POWER_DOWN_DELAY: Duration = Duration::from_micros(3);
+/// Time from the end of a release command until the part accepts commands
+/// again (tRES1), the slowest common value.
+pub const RELEASE_POWER_DOWN_DELAY: Duration = Duration::from_micros(30);
+
+/// Opcodes of a SPI NOR flash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlashCommandSet {
+    pub read: u8,
+    pub fast_read: u8,
+    /// Dummy cycles between the address and the data of `fast_read`.
+    pub fast_read_dummy: u8,
+    pub page_program: u8,
+    /// 4 KiB sector erase.
+    pub sector_erase: u8,
+    /// 64 KiB block erase.
+    pub block_erase: u8,
+    pub chip_erase: u8,
+    pub read_status: u8,
+    pub write_enable: u8,
+    pub deep_power_down: u8,
+    pub release_power_down: u8,
+}
+
+impl FlashCommandSet {
+    /// Opcodes shared by most parts.
+    pub const JEDEC: Self = Self {
+        read: 0x03,
+        fast_read: 0x0b,
+        fast_read_dummy: 8,
+        page_program: 0x02,
+        sector_erase: 0x20,
+        block_erase: 0xd8,
+        chip_erase: 0xc7,
+        read_status: 0x05,
+        write_enable: 0x06,
+        deep_power_down: 0xb9,
+        release_power_down: 0xab,
+    };
+
+    /// Command set for the part answering RDID (0x9f) with `id`: the matching
+    /// entry of `quirks`, [`Self::JEDEC`] if there is none.
+    pub fn for_jedec_id(id: [u8; 3], quirks: &[([u8; 3], FlashCommandSet)]) -> Self {
+        quirks
+            .iter()
+            .find(|(quirk_id, _)| *quirk_id == id)
+            .map_or(Self::JEDEC, |(_, cmds)| *cmds)
+    }
+}
+
+impl Default for FlashCommandSet {
+    fn default() -> Self {
+        Self::JEDEC
+    }
+}
@@ -1,8 +1,16 @@
+use core::ptr::NonNull;
+
 use tock_registers::{
+    interfaces::{ReadWriteable, Readable, Writeable},
     register_bitfields, register_structs,
     registers::{ReadWrite, WriteOnly},
 };
 
+use crate::{
+    kernel,
+    qspi::{DEEP_POWER_DOWN_DELAY, FlashCommandSet, RELEASE_POWER_DOWN_DELAY},
+};
+
 register_bitfields! [
     u32,
 
@@ -124,3 +132,296 @@ register_structs! {
         (0x034 => @END),
     }
 }
+
+/// Part of a flat flash range living on a single chip select.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DieSpan {
+    pub cs: u32,
+    /// Address inside the die.
+    pub addr: u32,
+    /// Offset into the caller's buffer.
+    pub offset: usize,
+    pub len: usize,
+}
+
+/// Iterator splitting a flat range at die boundaries, see [`die_spans`].
+#[derive(Debug, Clone)]
+pub struct DieSpans {
+    die_size: usize,
+    addr: usize,
+    offset: usize,
+    remaining: usize,
+}
+
+/// Split `len` bytes at `addr` of the concatenated address space of dies of
+/// `die_size` bytes into per chip select pieces.
+pub fn die_spans(die_size: usize, addr: usize, len: usize) -> DieSpans {
+    DieSpans {
+        die_size,
+        addr,
+        offset: 0,
+        remaining: len,
+    }
+}
+
+impl Iterator for DieSpans {
+    type Item = DieSpan;
+    fn next(&mut self) -> Option<DieSpan> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let in_die = self.addr % self.die_size;
+        let len = self.remaining.min(self.die_size - in_die);
+        let span = DieSpan {
+            cs: (self.addr / self.die_size) as u32,
+            addr: in_die as u32,
+            offset: self.offset,
+            len,
+        };
+        self.addr += len;
+        self.offset += len;
+        self.remaining -= len;
+        Some(span)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QspiError {
+    /// The range does not fit the flash.
+    OutOfRange,
+    /// The flash is mapped for execute in place and must stay awake.
+    Xip,
+    /// FLASH_CAPACITY holds a SIZE encoding without a documented die size.
+    UnknownCapacity,
+}
+
+#[derive(Debug)]
+pub struct PhytiumQspi {
+    base: NonNull<FlashControllerRegisters>,
+    cmds: FlashCommandSet,
+    /// bit n set while die n is in deep power-down
+    asleep: u32,
+}
+
+impl PhytiumQspi {
+    pub const fn new(base: *mut u8) -> Self {
+        Self {
+            base: NonNull::new(base).unwrap().cast(),
+            cmds: FlashCommandSet::JEDEC,
+            asleep: 0,
+        }
+    }
+
+    /// Switch to the opcodes of the attached part, see [`FlashCommandSet::for_jedec_id`].
+    pub fn set_command_set(&mut self, cmds: FlashCommandSet) {
+        self.cmds = cmds;
+        self.regs()
+            .rd_cfg
+            .modify(RdCfg::DUMMY_CYCLE.val(cmds.fast_read_dummy as u32));
+    }
+
+    pub fn command_set(&self) -> &FlashCommandSet {
+        &self.cmds
+    }
+
+    #[inline(always)]
+    const fn regs(&self) -> &FlashControllerRegisters {
+        unsafe { self.base.as_ref() }
+    }
+
+    /// Size of one flash device in bytes.
+    ///
+    /// Reserved encodings and the second 4 MiB one, whose meaning is not
+    /// documented, are reported rather than guessed.
+    pub fn die_size(&self) -> Result<usize, QspiError> {
+        use FlashCapacity::SIZE::Value;
+        const M: usize = 1024 * 1024;
+        match self.regs().flash_capacity.read_as_enum(FlashCapacity::SIZE) {
+            Some(Value::Bytes4M) => Ok(4 * M),
+            Some(Value::Bytes8M) => Ok(8 * M),
+            Some(Value::Bytes16M) => Ok(16 * M),
+            Some(Value::Bytes32M) => Ok(32 * M),
+            Some(Value::Bytes64M) => Ok(64 * M),
+            Some(Value::Bytes4M2) | None => Err(QspiError::UnknownCapacity),
+        }
+    }
+
+    /// Number of flash devices sharing the controller.
+    pub fn die_count(&self) -> usize {
+        self.regs().flash_capacity.read(FlashCapacity::NUM) as usize + 1
+    }
+
+    /// Size of the flat address space spanning all dies.
+    pub fn capacity(&self) -> Result<usize, QspiError> {
+        Ok(self.die_size()? * self.die_count())
+    }
+
+    pub fn select_die(&mut self, cs: u32) {
+        self.regs().cs_set.modify(CsSet::CHIP_SELECT.val(cs));
+    }
+
+    /// Run `f` on every die touched by `len` bytes at flat address `addr`,
+    /// with its chip select active, stopping at the first error.
+    pub fn for_each_die(
+        &mut self,
+        addr: usize,
+        len: usize,
+        mut f: impl FnMut(&mut Self, DieSpan) -> Result<(), QspiError>,
+    ) -> Result<(), QspiError> {
+        let die_size = self.die_size()?;
+        if addr
+            .checked_add(len)
+            .is_none_or(|end| end > die_size * self.die_count())
+        {
+            return Err(QspiError::OutOfRange);
+        }
+        let touched = die_spans(die_size, addr, len).fold(0, |dies, span| dies | 1 << span.cs);
+        self.wake_dies(touched);
+        for span in die_spans(die_size, addr, len) {
+            self.select_die(span.cs);
+            f(self, span)?;
+        }
+        Ok(())
+    }
+
+    /// Put every die into deep power-down to save standby power.
+    ///
+    /// A later operation wakes only the dies it touches, [`Self::flash_wake`]
+    /// all of them. Refused while XIP is on, nothing could fetch from the window.
+    pub fn flash_sleep(&mut self) -> Result<(), QspiError> {
+        if self.xip_enabled() {
+            return Err(QspiError::Xip);
+        }
+        let awake = ((1 << self.die_count()) - 1) & !self.asleep;
+        if awake == 0 {
+            return Ok(());
+        }
+        let opcode = self.cmds.deep_power_down;
+        self.for_each_cs(awake, |qspi| {
+            qspi.issue(opcode);
+            kernel::sleep(DEEP_POWER_DOWN_DELAY);
+        });
+        self.asleep |= awake;
+        Ok(())
+    }
+
+    /// Release every die from deep power-down, a no-op if they are awake.
+    pub fn flash_wake(&mut self) {
+        self.wake_dies(self.asleep);
+    }
+
+    /// Release the sleeping dies among `dies`, a bit per chip select.
+    fn wake_dies(&mut self, dies: u32) {
+        let dies = dies & self.asleep;
+        if dies == 0 {
+            return;
+        }
+        let opcode = self.cmds.release_power_down;
+        self.for_each_cs(dies, |qspi| {
+            qspi.issue(opcode);
+            kernel::sleep(RELEASE_POWER_DOWN_DELAY);
+        });
+        self.asleep &= !dies;
+    }
+
+    /// Send a command without address or data to the selected die.
+    ///
+    /// Completion is not polled, the opcode is out well within the tDP/tRES1
+    /// delay callers wait before the chip select may change.
+    fn issue(&self, opcode: u8) {
+        self.regs()
+            .cmd_port
+            .write(CmdPort::COMMAND.val(opcode as u32) + CmdPort::EXECUTE::SET);
+    }
+
+    /// Run `f` with each die of `dies` selected in turn, restoring the chip select.
+    fn for_each_cs(&mut self, dies: u32, mut f: impl FnMut(&Self)) {
+        let selected = self.regs().cs_set.read(CsSet::CHIP_SELECT);
+        for cs in (0..self.die_count() as u32).filter(|cs| dies & 1 << cs != 0) {
+            self.select_die(cs);
+            f(self);
+        }
+        self.select_die(selected);
+    }
+
+    /// Whether the flash is mapped for execute in place.
+    #[inline(always)]
+    pub fn xip_enabled(&self) -> bool {
+        self.regs().mode_reg.is_set(ModeReg::XIP_ENABLE)
+    }
+
+    /// Run a program/erase sequence with XIP turned off, restoring it afterwards.
+    ///
+    /// The flash window reads back garbage while the part is busy, so an image
+    /// executing from the same flash bricks itself if it runs the sequence in place.
+    ///
+    /// Copying `f` into a RAM buffer at run time is not offered: compiled Rust
+    /// is not position independent and calls into `core` and the rest of the
+    /// image, so only the linker can place code in RAM reliably. The guard
+    /// itself accesses MODE_REG and FLUSH with raw volatile pointers computed
+    /// before XIP goes off.
+    ///
+    /// # Safety
+    ///
+    /// - The caller, `f` and everything `f` calls must be linked to RAM, and so
+    ///   must this guard (it is generic, so it lands in the caller's crate as
+    ///   `*with_xip_suspended*`). In unoptimised builds that includes the
+    ///   `core::ptr` volatile accessors it calls.
+    /// - Nothing else may fetch from the flash window until this returns, mask
+    ///   interrupts whose handlers live in flash.
+    /// - `f` must wait for the write-in-progress bit to clear before returning
+    ///   and leave the dies awake, XIP fetches resume right after it.
+    #[inline(always)]
+    pub unsafe fn with_xip_suspended<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        const XIP: u32 = ModeReg::XIP_ENABLE::SET.value;
+        let mode = core::ptr::addr_of!(self.regs().mode_reg) as *mut u32;
+        let flush = core::ptr::addr_of!(self.regs().flush_reg) as *mut u32;
+
+        let was_enabled = unsafe { mode.read_volatile() } & XIP != 0;
+        if was_enabled {
+            unsafe { mode.write_volatile(mode.read_volatile() & !XIP) };
+        }
+        let ret = f(self);
+        if was_enabled {
+            // drop lines cached before the contents changed
+            unsafe {
+                flush.write_volatile(1);
+                mode.write_volatile(mode.read_volatile() | XIP);
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_die_spans() {
+        let spans: alloc::vec::Vec<_> = die_spans(0x100, 0xf0, 0x120).collect();
+        assert_eq!(
+            spans,
+            [
+                DieSpan {
+                    cs: 0,
+                    addr: 0xf0,
+                    offset: 0,
+                    len: 0x10
+                },
+                DieSpan {
+                    cs: 1,
+                    addr: 0,
+                    offset: 0x10,
+                    len: 0x100
+                },
+                DieSpan {
+                    cs: 2,
+                    addr: 0,
+                    offset: 0x110,
+                    len: 0x10
+                },
+            ]
+        );
+        assert_eq!(die_spans(0x100, 0x10, 0).count(), 0);
+    }
+}
@@ -0,0 +1,70 @@
+use alloc::vec::Vec;
+
+use crate::uart::pl011::PhytiumUart;
+
+/// A set of [`PhytiumUart`]s, addressed by index or name, e.g. `"console"`.
+#[derive(Debug, Default)]
+pub struct UartBank {
+    ports: Vec<(&'static str, PhytiumUart)>,
+}
+
+impl UartBank {
+    pub const fn new() -> Self {
+        Self { ports: Vec::new() }
+    }
+
+    /// Add a port, returns its index.
+    pub fn add(&mut self, name: &'static str, uart: PhytiumUart) -> usize {
+        self.ports.push((name, uart));
+        self.ports.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.ports.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ports.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&PhytiumUart> {
+        self.ports.get(index).map(|(_, uart)| uart)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut PhytiumUart> {
+        self.ports.get_mut(index).map(|(_, uart)| uart)
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.ports.iter().position(|(n, _)| *n == name)
+    }
+
+    pub fn by_name(&mut self, name: &str) -> Option<&mut PhytiumUart> {
+        let index = self.index_of(name)?;
+        self.get_mut(index)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&'static str, &mut PhytiumUart)> {
+        self.ports.iter_mut().map(|(name, uart)| (*name, uart))
+    }
+
+    /// Handler for an interrupt line shared by all ports: services every port
+    /// with a pending interrupt. Returns `false` if none was pending.
+    pub fn handle_interrupt(&mut self) -> bool {
+        let mut handled = false;
+        for (_, uart) in self.ports.iter_mut() {
+            if uart.irq_pending() {
+                uart.handle_interrupt();
+                handled = true;
+            }
+        }
+        handled
+    }
+
+    /// Handler for a port with its own interrupt line.
+    pub fn handle_port_interrupt(&mut self, index: usize) {
+        if let Some(uart) = self.get_mut(index) {
+            uart.handle_interrupt();
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Packet framing over a raw [`UartOps`] byte pipe, SLIP (RFC 1055) or COBS.
+
+use alloc::vec::Vec;
+
+use crate::uart::UartOps;
+
+const SLIP_END: u8 = 0xc0;
+const SLIP_ESC: u8 = 0xdb;
+const SLIP_ESC_END: u8 = 0xdc;
+const SLIP_ESC_ESC: u8 = 0xdd;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Slip,
+    /// Consistent overhead byte stuffing, frames delimited by `0x00`.
+    Cobs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    /// The frame is longer than the decoder limit, it was dropped.
+    Overflow,
+    /// Invalid escape sequence or COBS block.
+    Corrupt,
+}
+
+/// Append the encoded frame, delimiters included, to `out`.
+pub fn encode(codec: Codec, data: &[u8], out: &mut Vec<u8>) {
+    match codec {
+        Codec::Slip => slip_encode(data, out),
+        Codec::Cobs => cobs_encode(data, out),
+    }
+}
+
+fn slip_encode(data: &[u8], out: &mut Vec<u8>) {
+    // the leading END flushes line noise received before the frame
+    out.push(SLIP_END);
+    for &b in data {
+        match b {
+            SLIP_END => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+            SLIP_ESC => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+            b => out.push(b),
+        }
+    }
+    out.push(SLIP_END);
+}
+
+fn cobs_encode(data: &[u8], out: &mut Vec<u8>) {
+    let mut code_at = out.len();
+    let mut code = 1u8;
+    out.push(0);
+    for &b in data {
+        if b != 0 {
+            out.push(b);
+            code += 1;
+        }
+        if b == 0 || code == 0xff {
+            out[code_at] = code;
+            code_at = out.len();
+            code = 1;
+            out.push(0);
+        }
+    }
+    out[code_at] = code;
+    out.push(0);
+}
+
+fn cobs_decode(input: &[u8]) -> Result<Vec<u8>, FrameError> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        let code = input[i] as usize;
+        let end = i + code;
+        if code == 0 || end > input.len() {
+            return Err(FrameError::Corrupt);
+        }
+        out.extend_from_slice(&input[i + 1..end]);
+        i = end;
+        if code != 0xff && i < input.len() {
+            out.push(0);
+        }
+    }
+    Ok(out)
+}
+
+/// Incremental frame decoder, fed one received byte at a time.
+#[derive(Debug)]
+pub struct FrameDecoder {
+    codec: Codec,
+    max_len: usize,
+    buf: Vec<u8>,
+    escape: bool,
+    error: Option<FrameError>,
+}
+
+impl FrameDecoder {
+    /// Frames whose encoded size exceeds `max_len` are reported as [`FrameError::Overflow`].
+    pub fn new(codec: Codec, max_len: usize) -> Self {
+        Self {
+            codec,
+            max_len,
+            buf: Vec::new(),
+            escape: false,
+            error: None,
+        }
+    }
+
+    /// Returns the frame completed by `b`, if any. Empty frames are skipped.
+    pub fn push(&mut self, b: u8) -> Option<Result<Vec<u8>, FrameError>> {
+        let delimiter = match self.codec {
+            Codec::Slip => SLIP_END,
+            Codec::Cobs => 0,
+        };
+        if b == delimiter {
+            let raw = core::mem::take(&mut self.buf);
+            let escape = core::mem::replace(&mut self.escape, false);
+            if let Some(e) = self.error.take() {
+                return Some(Err(e));
+            }
+            if raw.is_empty() {
+                return None;
+            }
+            return Some(match self.codec {
+                Codec::Slip if escape => Err(FrameError::Corrupt),
+                Codec::Slip => Ok(raw),
+                Codec::Cobs => cobs_decode(&raw),
+            });
+        }
+        if self.error.is_some() {
+            return None;
+        }
+        let b = match self.codec {
+            Codec::Slip if self.escape => {
+                self.escape = false;
+                match b {
+                    SLIP_ESC_END => SLIP_END,
+                    SLIP_ESC_ESC => SLIP_ESC,
+                    _ => {
+                        self.error = Some(FrameError::Corrupt);
+                        return None;
+                    }
+                }
+            }
+            Codec::Slip if b == SLIP_ESC => {
+                self.escape = true;
+                return None;
+            }
+            _ => b,
+        };
+        if self.buf.len() >= self.max_len {
+            self.buf.clear();
+            self.error = Some(FrameError::Overflow);
+            return None;
+        }
+        self.buf.push(b);
+        None
+    }
+}
+
+/// Encode and transmit one frame.
+pub async fn send_frame<U: UartOps>(uart: &mut U, codec: Codec, data: &[u8]) {
+    let mut out = Vec::with_capacity(data.len() + 2);
+    encode(codec, data, &mut out);
+    let mut sent = 0;
+    while sent < out.len() {
+        sent += uart.write_bytes(&out[sent..]).await;
+    }
+}
+
+/// Receive the next frame, the decoder keeps partial frames between calls.
+pub async fn recv_frame<U: UartOps>(
+    uart: &mut U,
+    decoder: &mut FrameDecoder,
+) -> Result<Vec<u8>, FrameError> {
+    let mut b = [0u8; 1];
+    loop {
+        if uart.read_bytes(&mut b).await == 0 {
+            continue;
+        }
+        if let Some(frame) = decoder.push(b[0]) {
+            return frame;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(codec: Codec, data: &[u8]) {
+        let mut wire = Vec::new();
+        encode(codec, data, &mut wire);
+        let mut decoder = FrameDecoder::new(codec, 1024);
+        let frames: Vec<_> = wire.iter().filter_map(|&b| decoder.push(b)).collect();
+        assert_eq!(frames, [Ok(data.to_vec())]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let long: Vec<u8> = (0..600).map(|i| (i % 255 + 1) as u8).collect();
+        for codec in [Codec::Slip, Codec::Cobs] {
+            round_trip(codec, b"hello");
+            round_trip(codec, &[0, 0xc0, 0xdb, 0xdc, 0xdd, 0]);
+            round_trip(codec, &long);
+        }
+    }
+
+    #[test]
+    fn test_cobs_vectors() {
+        let mut wire = Vec::new();
+        cobs_encode(&[0x11, 0x22, 0x00, 0x33], &mut wire);
+        assert_eq!(wire, [0x03, 0x11, 0x22, 0x02, 0x33, 0x00]);
+        assert_eq!(cobs_decode(&[0x05, 0x11]), Err(FrameError::Corrupt));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut decoder = FrameDecoder::new(Codec::Slip, 2);
+        let frames: Vec<_> = [SLIP_END, 1, 2, 3, SLIP_END, 4, SLIP_END]
+            .iter()
+            .filter_map(|&b| decoder.push(b))
+            .collect();
+        assert_eq!(frames, [Err(FrameError::Overflow), Ok(alloc::vec![4])]);
+    }
+}
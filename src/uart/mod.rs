@@ -1 +1,23 @@
+pub mod bank;
+pub mod framing;
 pub mod pl011;
+mod ring;
+pub mod test_util;
+
+/// Async byte pipe implemented by the UART drivers.
+pub trait UartOps {
+    /// Write all of `bytes`, returns the number written.
+    fn write_bytes<'a>(&'a mut self, bytes: &'a [u8]) -> impl Future<Output = usize> + 'a;
+    /// Read at least one byte.
+    fn read_bytes<'a>(&'a mut self, buf: &'a mut [u8]) -> impl Future<Output = usize> + 'a;
+}
+
+impl UartOps for pl011::PhytiumUart {
+    fn write_bytes<'a>(&'a mut self, bytes: &'a [u8]) -> impl Future<Output = usize> + 'a {
+        pl011::PhytiumUart::write_bytes(self, bytes)
+    }
+
+    fn read_bytes<'a>(&'a mut self, buf: &'a mut [u8]) -> impl Future<Output = usize> + 'a {
+        pl011::PhytiumUart::read_bytes(self, buf)
+    }
+}
@@ -1,18 +1,37 @@
-use core::ptr::NonNull;
+use core::{
+    ptr::NonNull,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+    time::Duration,
+};
 use futures::task::AtomicWaker;
 use tock_registers::{
-    interfaces::{Readable, Writeable},
+    LocalRegisterCopy,
+    fields::FieldValue,
+    interfaces::{ReadWriteable, Readable, Writeable},
     register_bitfields, register_structs,
     registers::{ReadOnly, ReadWrite, WriteOnly},
 };
 
-use crate::uart::pl011::INTERRUPT::{RXIM, TXIM};
+use crate::{
+    kernel,
+    misc::{
+        poll::{PollPolicy, PollTimeout},
+        units,
+        watchdog::Watchdog,
+    },
+    uart::{
+        pl011::INTERRUPT::{RXIM, TXIM},
+        ring::SpscRing,
+    },
+};
 
 register_structs! {
     PhytiumUartRegs {
         /// Data Register.
         (0x00 => dr: ReadWrite<u32, DATA::Register>),
-        (0x04 => _reserved0),
+        /// Receive Status Register / Error Clear Register.
+        (0x04 => rsr: ReadWrite<u32, RSR::Register>),
+        (0x08 => _reserved0),
         /// Flag Register.
         (0x18 => fr: ReadOnly<u32, FLAG::Register>),
         (0x1c => _reserved1),
@@ -28,12 +47,15 @@ register_structs! {
         /// Interrupt Mask Set Clear Register.
         (0x38 => imsc: ReadWrite<u32, INTERRUPT::Register>),
         /// Raw Interrupt Status Register.
-        (0x3c => ris: ReadOnly<u32>),
+        (0x3c => ris: ReadOnly<u32, INTERRUPT::Register>),
         /// Masked Interrupt Status Register.
-        (0x40 => mis: ReadOnly<u32>),
+        (0x40 => mis: ReadOnly<u32, INTERRUPT::Register>),
         /// Interrupt Clear Register.
         (0x44 => icr: WriteOnly<u32,INTERRUPT::Register>),
-        (0x48 => @END),
+        (0x48 => _reserved2),
+        /// Peripheral Identification Registers.
+        (0xfe0 => periph_id: [ReadOnly<u32>; 4]),
+        (0xff0 => @END),
     }
 }
 
@@ -45,6 +67,12 @@ register_bitfields![u32,
         BE OFFSET(11) NUMBITS(1),
         OE OFFSET(12) NUMBITS(1),
     ],
+    RSR [
+        FE OFFSET(0) NUMBITS(1),
+        PE OFFSET(1) NUMBITS(1),
+        BE OFFSET(2) NUMBITS(1),
+        OE OFFSET(3) NUMBITS(1),
+    ],
     FLAG [
         CTS OFFSET(0) NUMBITS(1),
         DSR OFFSET(1) NUMBITS(1),
@@ -71,7 +99,8 @@ register_bitfields![u32,
     ],
     CONTROLL [
         ENABLE OFFSET(0) NUMBITS(1) [],
-        RSV OFFSET(1) NUMBITS(7) [],
+        RSV OFFSET(1) NUMBITS(6) [],
+        LBE OFFSET(7) NUMBITS(1) [],
         TXE OFFSET(8) NUMBITS(1) [],
         RXE OFFSET(9) NUMBITS(1) [],
     ],
@@ -94,15 +123,359 @@ register_bitfields![u32,
     INTERRUPT [
         RXIM OFFSET(4) NUMBITS(1),
         TXIM OFFSET(5) NUMBITS(1),
+        RTIM OFFSET(6) NUMBITS(1),
+        OEIM OFFSET(10) NUMBITS(1),
     ]
 ];
 
+/// Baud rates tried by [`PhytiumUart::detect_baud`], fastest first.
+pub const STANDARD_BAUD_RATES: [u32; 8] = [
+    921_600, 460_800, 230_400, 115_200, 57_600, 38_400, 19_200, 9_600,
+];
+
+/// Quiet bit periods after which the receive timeout interrupt fires, fixed by the PL011.
+pub const RX_IDLE_BITS: u32 = 32;
+
+/// TX FIFO polls before [`PhytiumUart::write_byte_raw_unchecked`] drops its byte.
+const RAW_WRITE_SPINS: usize = 1 << 20;
+
+/// Character the peer sends while the baud rate is detected.
+const AUTOBAUD_CHAR: u32 = 0x55;
+/// Consecutive clean characters needed to accept a rate.
+const AUTOBAUD_SAMPLES: u32 = 8;
+
+/// FIFO interrupt trigger level, as a fraction of the FIFO depth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FifoLevel {
+    OneEighth = 0,
+    OneQuarter = 1,
+    Half = 2,
+    ThreeQuarters = 3,
+    SevenEighths = 4,
+}
+
+/// Trade-off between interrupt rate and latency for the interrupt driven mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FifoWatermark {
+    /// Interrupt on the first received bytes and refill TX early.
+    LowLatency,
+    /// The `init_irq` default.
+    Balanced,
+    /// Let the FIFOs fill up before interrupting, with a deep software buffer.
+    Throughput,
+}
+
+impl FifoWatermark {
+    /// RX and TX trigger levels.
+    pub const fn levels(self) -> (FifoLevel, FifoLevel) {
+        match self {
+            Self::LowLatency => (FifoLevel::OneEighth, FifoLevel::Half),
+            Self::Balanced => (FifoLevel::Half, FifoLevel::ThreeQuarters),
+            Self::Throughput => (FifoLevel::ThreeQuarters, FifoLevel::OneEighth),
+        }
+    }
+
+    /// Software RX buffer size for a hardware FIFO of `fifo_depth` bytes.
+    pub const fn rx_buffer_size(self, fifo_depth: usize) -> usize {
+        match self {
+            Self::LowLatency => fifo_depth * 2,
+            Self::Balanced => fifo_depth * 4,
+            Self::Throughput => fifo_depth * 16,
+        }
+    }
+}
+
+/// RS485 half-duplex transceiver control.
+#[derive(Debug, Clone, Copy)]
+pub struct Rs485Config {
+    /// Drive the transceiver DE pin, `true` enables the line driver.
+    pub set_de: fn(bool),
+    /// Delay between asserting DE and the first start bit.
+    pub delay_before_send: Duration,
+    /// Delay between the last stop bit leaving the shifter and releasing DE.
+    pub delay_after_send: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DataBits {
+    Five,
+    Six,
+    Seven,
+    Eight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+    /// Parity bit always 1.
+    Mark,
+    /// Parity bit always 0.
+    Space,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+/// Character framing, 8N1 by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineConfig {
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+}
+
+impl Default for LineConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl LineConfig {
+    pub const DEFAULT: Self = Self {
+        data_bits: DataBits::Eight,
+        parity: Parity::None,
+        stop_bits: StopBits::One,
+    };
+
+    fn lcr_h(&self) -> FieldValue<u32, CONTROLH::Register> {
+        let wlen = match self.data_bits {
+            DataBits::Five => CONTROLH::WLEN::len5,
+            DataBits::Six => CONTROLH::WLEN::len6,
+            DataBits::Seven => CONTROLH::WLEN::len7,
+            DataBits::Eight => CONTROLH::WLEN::len8,
+        };
+        let parity = match self.parity {
+            Parity::None => CONTROLH::PEN::CLEAR,
+            Parity::Odd => CONTROLH::PEN::SET,
+            Parity::Even => CONTROLH::PEN::SET + CONTROLH::EPS::SET,
+            Parity::Mark => CONTROLH::PEN::SET + CONTROLH::SPS::SET,
+            Parity::Space => CONTROLH::PEN::SET + CONTROLH::EPS::SET + CONTROLH::SPS::SET,
+        };
+        let stop = match self.stop_bits {
+            StopBits::One => CONTROLH::STP2::CLEAR,
+            StopBits::Two => CONTROLH::STP2::SET,
+        };
+        wlen + parity + stop
+    }
+
+    const fn data_bit_count(&self) -> u32 {
+        match self.data_bits {
+            DataBits::Five => 5,
+            DataBits::Six => 6,
+            DataBits::Seven => 7,
+            DataBits::Eight => 8,
+        }
+    }
+
+    /// Bits on the wire per character, start and stop bits included.
+    pub const fn frame_bits(&self) -> u32 {
+        let parity = match self.parity {
+            Parity::None => 0,
+            _ => 1,
+        };
+        let stop = match self.stop_bits {
+            StopBits::One => 1,
+            StopBits::Two => 2,
+        };
+        1 + self.data_bit_count() + parity + stop
+    }
+
+    /// Parity bit the transmitter appends to `b`, `None` without parity.
+    fn parity_bit(&self, b: u8) -> Option<bool> {
+        let bits = self.data_bit_count();
+        let odd_ones = (b & (0xffu16 >> (8 - bits)) as u8).count_ones() % 2 == 1;
+        match self.parity {
+            Parity::None => None,
+            Parity::Odd => Some(!odd_ones),
+            Parity::Even => Some(odd_ones),
+            Parity::Mark => Some(true),
+            Parity::Space => Some(false),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UartError {
+    /// The transmitter did not go idle within the [`PollPolicy`].
+    Timeout,
+    /// The operation needs parity enabled in the [`LineConfig`].
+    ParityDisabled,
+    /// The baud rate is zero or out of reach of the divisors at this clock.
+    InvalidBaud,
+}
+
+impl From<PollTimeout> for UartError {
+    fn from(_: PollTimeout) -> Self {
+        Self::Timeout
+    }
+}
+
+/// Result of [`PhytiumUart::parity_error_test`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParityTestReport {
+    /// Characters sent with a wrong parity bit.
+    pub sent: usize,
+    /// NAK bytes the peer answered with.
+    pub naks: usize,
+}
+
+/// Snapshot of a port's settings, see [`PhytiumUart::config`].
+///
+/// Restore it on a fresh instance with [`UartConfig::apply`], e.g. after a
+/// device reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UartConfig {
+    pub clock_hz: u32,
+    pub baud_rate: u32,
+    pub line: LineConfig,
+    pub watermark: FifoWatermark,
+    /// See [`PhytiumUart::set_tx_fifo_trigger`].
+    pub tx_trigger: Option<FifoLevel>,
+    /// Interrupt driven (`init_irq`) rather than polled (`init_no_irq`).
+    pub irq: bool,
+    pub rx_timestamps: bool,
+}
+
+impl UartConfig {
+    /// Polled 8N1 at `baud_rate`.
+    pub const fn new(clock_hz: u32, baud_rate: u32) -> Self {
+        Self {
+            clock_hz,
+            baud_rate,
+            line: LineConfig::DEFAULT,
+            watermark: FifoWatermark::Balanced,
+            tx_trigger: None,
+            irq: false,
+            rx_timestamps: false,
+        }
+    }
+
+    pub const fn line(mut self, line: LineConfig) -> Self {
+        self.line = line;
+        self
+    }
+
+    pub const fn watermark(mut self, watermark: FifoWatermark) -> Self {
+        self.watermark = watermark;
+        self
+    }
+
+    pub const fn tx_trigger(mut self, level: Option<FifoLevel>) -> Self {
+        self.tx_trigger = level;
+        self
+    }
+
+    pub const fn irq(mut self, irq: bool) -> Self {
+        self.irq = irq;
+        self
+    }
+
+    pub const fn rx_timestamps(mut self, enable: bool) -> Self {
+        self.rx_timestamps = enable;
+        self
+    }
+
+    /// Program `uart` with these settings through the matching `init_*`.
+    ///
+    /// Settings that cannot be programmed, e.g. a snapshot of a port never
+    /// initialised or a corrupted stored copy, leave `uart` untouched.
+    pub fn apply(&self, uart: &mut PhytiumUart) -> Result<(), UartError> {
+        if !PhytiumUart::divisors_valid(self.clock_hz, self.baud_rate) {
+            return Err(UartError::InvalidBaud);
+        }
+        uart.line = self.line;
+        uart.watermark = self.watermark;
+        uart.tx_trigger = self.tx_trigger;
+        uart.rx_timestamps = self.rx_timestamps;
+        if self.irq {
+            uart.init_irq(self.clock_hz, self.baud_rate);
+        } else {
+            uart.init_no_irq(self.clock_hz, self.baud_rate);
+        }
+        Ok(())
+    }
+}
+
+/// Magic sequence arming a [`SysrqHook`].
+#[derive(Debug, Clone, Copy)]
+pub enum SysrqTrigger {
+    /// A received break condition.
+    Break,
+    /// A fixed, non-empty byte sequence. The bytes are still delivered.
+    Sequence(&'static [u8]),
+}
+
+/// Emergency command hook on the RX path, see [`PhytiumUart::set_sysrq`].
+#[derive(Debug, Clone, Copy)]
+pub struct SysrqHook {
+    pub trigger: SysrqTrigger,
+    /// Called with the command byte received right after the trigger, which is
+    /// not delivered to readers. Runs in interrupt context in irq mode.
+    pub handler: fn(u8),
+}
+
+/// Port registered with [`PhytiumUart::set_console`].
+static CONSOLE: AtomicPtr<PhytiumUartRegs> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Mask the console interrupts and hand out a polled handle to it, see [`crate::panic_quiesce`].
+pub(crate) fn quiesce_console() -> Option<PhytiumUart> {
+    let base = CONSOLE.load(Ordering::Acquire);
+    if base.is_null() {
+        return None;
+    }
+    let uart = PhytiumUart::new(base.cast());
+    let regs = uart.regs();
+    regs.imsc.set(0);
+    regs.icr.set(0x7ff);
+    // keep the programmed baud rate and framing, only make sure it can send
+    regs.cr_l.modify(CONTROLL::ENABLE::SET + CONTROLL::TXE::SET);
+    Some(uart)
+}
+
 #[derive(Debug)]
 pub struct PhytiumUart {
     base: NonNull<PhytiumUartRegs>,
     waker: AtomicWaker,
     tx_irq_cnt: usize,
     rx_irq_cnt: usize,
+    rs485: Option<Rs485Config>,
+    /// (imsc, cr_l) saved by `enter_standby`
+    standby: Option<(u32, u32)>,
+    woken: bool,
+    /// 0 means read it from the peripheral ID
+    fifo_depth: usize,
+    watermark: FifoWatermark,
+    /// overrides the TX level of `watermark`
+    tx_trigger: Option<FifoLevel>,
+    rx_ring: SpscRing<u8>,
+    rx_waker: AtomicWaker,
+    rx_dropped: usize,
+    rx_overruns: usize,
+    sysrq: Option<SysrqHook>,
+    /// trigger progress, equal to the trigger length once armed
+    sysrq_matched: usize,
+    line: LineConfig,
+    poll: PollPolicy,
+    rx_idle_events: AtomicUsize,
+    idle_waker: AtomicWaker,
+    rx_timestamps: bool,
+    /// arrival time of every byte in `rx_ring`, pushed first and popped last
+    rx_stamps: SpscRing<u64>,
+    clock_hz: u32,
+    baud_rate: u32,
+    irq_mode: bool,
 }
 
 impl PhytiumUart {
@@ -112,17 +485,47 @@ impl PhytiumUart {
             waker: AtomicWaker::new(),
             rx_irq_cnt: 0,
             tx_irq_cnt: 0,
+            rs485: None,
+            standby: None,
+            woken: false,
+            fifo_depth: 0,
+            watermark: FifoWatermark::Balanced,
+            tx_trigger: None,
+            rx_ring: SpscRing::new(),
+            rx_waker: AtomicWaker::new(),
+            rx_dropped: 0,
+            rx_overruns: 0,
+            sysrq: None,
+            sysrq_matched: 0,
+            line: LineConfig::DEFAULT,
+            poll: PollPolicy::DEFAULT,
+            rx_idle_events: AtomicUsize::new(0),
+            idle_waker: AtomicWaker::new(),
+            rx_timestamps: false,
+            rx_stamps: SpscRing::new(),
+            clock_hz: 0,
+            baud_rate: 0,
+            irq_mode: false,
         }
     }
+    /// Whether `baude_rate` gives an integer divisor within 1..=0xffff.
+    fn divisors_valid(clock_hz: u32, baude_rate: u32) -> bool {
+        let baude_rate_16 = 16 * baude_rate as u64;
+        baude_rate != 0 && (1..=0xffff).contains(&(clock_hz as u64 / baude_rate_16))
+    }
     fn get_ti_tf(clock_hz: u32, baude_rate: u32) -> (u32, u32) {
         let baude_rate_16 = 16 * baude_rate;
         let ti = clock_hz / baude_rate_16;
         let tf = clock_hz % baude_rate_16;
-        let tf = (tf * 64 + (baude_rate_16 >> 1)) / baude_rate_16;
-        (ti, tf)
+        // tf * 64 overflows u32 above 4M baud
+        let tf = (tf as u64 * 64 + (baude_rate_16 >> 1) as u64) / baude_rate_16 as u64;
+        (ti, tf as u32)
     }
-    /// no irq, no fifo, 8bits data, 1 stop bit, no odd-even check
+    /// no irq, no fifo, framing from [`Self::set_line_config`] (8N1 by default)
     pub fn init_no_irq(&mut self, clock_hz: u32, baude_rate: u32) {
+        self.clock_hz = clock_hz;
+        self.baud_rate = baude_rate;
+        self.irq_mode = false;
         // disable reg
         let regs = self.regs();
         regs.cr_l.write(CONTROLL::ENABLE::CLEAR);
@@ -132,8 +535,8 @@ impl PhytiumUart {
         regs.tibd.set(ti);
         regs.tfbd.set(tf);
 
-        // width 8 , no check, stop bit 1
-        regs.cr_h.write(CONTROLH::WLEN::len8);
+        // width, check, stop bits
+        regs.cr_h.write(self.line.lcr_h());
 
         // no interrupt
         regs.imsc.set(0);
@@ -142,10 +545,14 @@ impl PhytiumUart {
         regs.cr_l
             .write(CONTROLL::ENABLE::SET + CONTROLL::TXE::SET + CONTROLL::RXE::SET);
     }
-    /// rx and tx irq, 1/2 fifo, 8bits data, 1 stop bit, no odd-even check
+    /// rx and tx irq, fifo levels from the [`FifoWatermark`] (1/2 by default), framing from [`Self::set_line_config`] (8N1 by default)
     pub fn init_irq(&mut self, clock_hz: u32, baude_rate: u32) {
-        // disable reg
+        self.clock_hz = clock_hz;
+        self.baud_rate = baude_rate;
+        self.irq_mode = true;
+        // disable reg, and keep a live handler off the buffers swapped below
         let regs = self.regs();
+        regs.imsc.set(0);
         regs.cr_l.write(CONTROLL::ENABLE::CLEAR);
 
         // set bd rate
@@ -153,52 +560,678 @@ impl PhytiumUart {
         regs.tibd.set(ti);
         regs.tfbd.set(tf);
 
-        // width 8, no check, stop bit 1, fifo
-        regs.cr_h.write(CONTROLH::WLEN::len8 + CONTROLH::FEN::SET);
+        // width, check, stop bits, fifo
+        regs.cr_h.write(self.line.lcr_h() + CONTROLH::FEN::SET);
 
-        // tx and rx fifo 1/2
-        regs.ifls.write(FIFO::RXSEL::RX1_2 + FIFO::TXSEL::TX3_4);
+        // tx and rx fifo levels
+        self.program_fifo_levels();
+        self.alloc_rx_buffers();
 
-        // tx and rx interrupt
-        regs.imsc.write(RXIM::SET + TXIM::SET);
+        // tx and rx interrupt, rx timeout flushes bytes below the rx level,
+        // overrun to recover from a stalled consumer
+        let regs = self.regs();
+        regs.imsc
+            .write(RXIM::SET + TXIM::SET + INTERRUPT::RTIM::SET + INTERRUPT::OEIM::SET);
 
         // enable uart ,rx, tx
         regs.cr_l
             .write(CONTROLL::ENABLE::SET + CONTROLL::TXE::SET + CONTROLL::RXE::SET);
     }
+
+    /// Current settings, as last programmed by `init_*` and the setters.
+    pub fn config(&self) -> UartConfig {
+        UartConfig {
+            clock_hz: self.clock_hz,
+            baud_rate: self.baud_rate,
+            line: self.line,
+            watermark: self.watermark,
+            tx_trigger: self.tx_trigger,
+            irq: self.irq_mode,
+            rx_timestamps: self.rx_timestamps,
+        }
+    }
+
+    /// Change the character framing, applied right away and kept across `init_*`.
+    pub fn set_line_config(&mut self, line: LineConfig) -> Result<(), UartError> {
+        self.line = line;
+        let fen = self.regs().cr_h.read(CONTROLH::FEN);
+        self.update_lcr_h(line.lcr_h() + CONTROLH::FEN.val(fen))
+    }
+
+    pub fn line_config(&self) -> LineConfig {
+        self.line
+    }
+
+    /// Reprogram LCR_H, which must not change while a character is on the wire.
+    fn update_lcr_h(&self, value: FieldValue<u32, CONTROLH::Register>) -> Result<(), UartError> {
+        self.wait_tx_idle()?;
+        let regs = self.regs();
+        let cr_l = regs.cr_l.get();
+        regs.cr_l.modify(CONTROLL::ENABLE::CLEAR);
+        regs.cr_h.write(value);
+        regs.cr_l.set(cr_l);
+        Ok(())
+    }
+
+    /// Time one character takes on the wire with the current framing and baud rate,
+    /// [`Duration::MAX`] on a port never initialised.
+    pub fn char_time(&self) -> Duration {
+        units::bit_times(self.line.frame_bits(), self.baud_rate)
+    }
+
+    /// Quiet time after which the receive timeout interrupt fires, see [`RX_IDLE_BITS`].
+    pub fn rx_idle_time(&self) -> Duration {
+        units::bit_times(RX_IDLE_BITS, self.baud_rate)
+    }
+
+    /// Make this port the system console, kept usable by [`crate::panic_quiesce`].
+    pub fn set_console(&self) {
+        CONSOLE.store(self.base.as_ptr(), Ordering::Release);
+    }
+
+    /// Reference clock the platform reports for this port, see
+    /// [`Kernel::uart_clock_hz`](crate::kernel::Kernel::uart_clock_hz).
+    ///
+    /// Pass it to `init_*` instead of a hard coded frequency.
+    pub fn platform_clock_hz(&self) -> Option<u32> {
+        kernel::uart_clock_hz(self.base.as_ptr() as usize)
+    }
+
+    /// Follow a reference clock change reported by the platform, e.g. after DVFS.
+    ///
+    /// Recomputes the divisors for the current baud rate and returns whether
+    /// anything was reprogrammed. A port that was never initialised, or a
+    /// platform not reporting its clocks, is left alone. If the new clock
+    /// cannot divide down to the baud rate the port keeps its old divisors and
+    /// [`UartError::InvalidBaud`] is returned.
+    pub fn sync_clock(&mut self) -> Result<bool, UartError> {
+        let Some(clock_hz) = self.platform_clock_hz() else {
+            return Ok(false);
+        };
+        if self.baud_rate == 0 || clock_hz == self.clock_hz {
+            return Ok(false);
+        }
+        self.update_divisors(clock_hz)?;
+        self.clock_hz = clock_hz;
+        Ok(true)
+    }
+
+    /// Reprogram IBRD/FBRD for `clock_hz` at the current baud rate.
+    fn update_divisors(&self, clock_hz: u32) -> Result<(), UartError> {
+        if !Self::divisors_valid(clock_hz, self.baud_rate) {
+            return Err(UartError::InvalidBaud);
+        }
+        self.wait_tx_idle()?;
+        let regs = self.regs();
+        let cr_l = regs.cr_l.get();
+        regs.cr_l.modify(CONTROLL::ENABLE::CLEAR);
+        let (ti, tf) = Self::get_ti_tf(clock_hz, self.baud_rate);
+        regs.tibd.set(ti);
+        regs.tfbd.set(tf);
+        // the divisors only latch on a LCR_H write
+        regs.cr_h.set(regs.cr_h.get());
+        regs.cr_l.set(cr_l);
+        Ok(())
+    }
+
+    /// Internally route TX back to RX, for self tests without a cable.
+    pub fn set_loopback(&mut self, enable: bool) {
+        self.regs().cr_l.modify(CONTROLL::LBE.val(enable as u32));
+    }
+
+    /// Bound the waits for the transmitter, [`PollPolicy::DEFAULT`] if never set.
+    pub fn set_poll_policy(&mut self, poll: PollPolicy) {
+        self.poll = poll;
+    }
+
+    /// Wait until the TX FIFO is empty and the last stop bit has left the shifter.
+    fn wait_tx_idle(&self) -> Result<(), PollTimeout> {
+        self.poll.wait(|| !self.regs().fr.is_set(FLAG::BUSY))
+    }
+
+    /// Transmit `b` with the parity bit inverted, by temporarily forcing stick parity.
+    pub fn send_bad_parity(&mut self, b: u8) -> Result<(), UartError> {
+        let Some(parity) = self.line.parity_bit(b) else {
+            return Err(UartError::ParityDisabled);
+        };
+        let fen = CONTROLH::FEN.val(self.regs().cr_h.read(CONTROLH::FEN));
+        // with SPS set, EPS selects a constant 0 parity bit, clear selects 1
+        let eps = if parity {
+            CONTROLH::EPS::SET
+        } else {
+            CONTROLH::EPS::CLEAR
+        };
+        let mut stuck = self.line;
+        stuck.parity = Parity::Mark;
+        self.update_lcr_h(stuck.lcr_h() + eps + fen)?;
+        self.rs485_begin();
+        self.regs().dr.set(b as u32);
+        self.rs485_end();
+        self.update_lcr_h(self.line.lcr_h() + fen)
+    }
+
+    /// Parity error injection test, polled mode.
+    ///
+    /// Sends every byte of `bytes` with a wrong parity bit and counts the
+    /// `nak` bytes received within `window` after each of them.
+    pub fn parity_error_test(
+        &mut self,
+        bytes: &[u8],
+        nak: u8,
+        window: Duration,
+    ) -> Result<ParityTestReport, UartError> {
+        let mut report = ParityTestReport::default();
+        for &b in bytes {
+            self.send_bad_parity(b)?;
+            report.sent += 1;
+            let deadline = kernel::now() + window;
+            while kernel::now() < deadline {
+                if self.regs().fr.is_set(FLAG::RXFE) {
+                    continue;
+                }
+                let data = self.regs().dr.extract();
+                if self.filter_sysrq(data) == Some(nak) {
+                    report.naks += 1;
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// FIFO depth in bytes, read from the peripheral ID unless set with [`Self::set_fifo_depth`].
+    pub fn fifo_depth(&self) -> usize {
+        if self.fifo_depth != 0 {
+            return self.fifo_depth;
+        }
+        // PL011 r1p5 (revision 3) and later have 32 byte FIFOs
+        let revision = (self.regs().periph_id[2].get() >> 4) & 0xf;
+        if revision >= 3 { 32 } else { 16 }
+    }
+
+    /// Override the detected FIFO depth, for clones with unreliable ID registers.
+    pub fn set_fifo_depth(&mut self, depth: usize) {
+        self.fifo_depth = depth;
+    }
+
+    /// Select the FIFO trigger levels and software RX buffer size.
+    ///
+    /// Takes effect immediately if the port is already interrupt driven,
+    /// bytes still in the software buffer are discarded.
+    pub fn set_fifo_watermark(&mut self, watermark: FifoWatermark) {
+        self.watermark = watermark;
+        if self.rx_ring.capacity() == 0 {
+            // applied by init_irq
+            return;
+        }
+        let imsc = self.regs().imsc.get();
+        self.regs().imsc.set(0);
+        self.program_fifo_levels();
+        self.alloc_rx_buffers();
+        self.regs().imsc.set(imsc);
+    }
+
+    /// TX interrupt level independent of the [`FifoWatermark`], `None` to follow it again.
+    pub fn set_tx_fifo_trigger(&mut self, level: Option<FifoLevel>) {
+        self.tx_trigger = level;
+        if self.rx_ring.capacity() == 0 {
+            // applied by init_irq
+            return;
+        }
+        self.program_fifo_levels();
+    }
+
+    /// Tag every received byte with its arrival time, for [`Self::read_bytes_timestamped`].
+    ///
+    /// Bytes still in the software buffer are discarded.
+    pub fn set_rx_timestamps(&mut self, enable: bool) {
+        self.rx_timestamps = enable;
+        if self.rx_ring.capacity() == 0 {
+            // applied by init_irq
+            return;
+        }
+        let imsc = self.regs().imsc.get();
+        self.regs().imsc.set(0);
+        self.alloc_rx_buffers();
+        self.regs().imsc.set(imsc);
+    }
+
+    fn alloc_rx_buffers(&mut self) {
+        let size = self.watermark.rx_buffer_size(self.fifo_depth());
+        self.rx_ring = SpscRing::with_capacity(size);
+        // a reader between popping bytes and their stamps lets the interrupt
+        // push up to `size` more stamps
+        self.rx_stamps = if self.rx_timestamps {
+            SpscRing::with_capacity(size * 2)
+        } else {
+            SpscRing::new()
+        };
+    }
+
+    fn program_fifo_levels(&self) {
+        let (rx, tx) = self.watermark.levels();
+        let tx = self.tx_trigger.unwrap_or(tx);
+        self.regs()
+            .ifls
+            .write(FIFO::RXSEL.val(rx as u32) + FIFO::TXSEL.val(tx as u32));
+    }
+
+    /// Bytes dropped because the software RX buffer was full.
+    pub fn rx_dropped(&self) -> usize {
+        self.rx_dropped
+    }
+
+    /// Times the RX FIFO overflowed before the interrupt handler emptied it.
+    pub fn rx_overruns(&self) -> usize {
+        self.rx_overruns
+    }
+
+    /// Drop everything received so far, in the FIFO and the software buffer,
+    /// and clear the receive error flags.
+    pub fn flush_rx(&mut self) {
+        // the interrupt handler reads DR too
+        let regs = self.regs();
+        let imsc = regs.imsc.get();
+        regs.imsc.set(0);
+        while !regs.fr.is_set(FLAG::RXFE) {
+            regs.dr.get();
+        }
+        regs.rsr.set(0);
+        self.rx_ring.skip(usize::MAX);
+        self.rx_stamps.skip(usize::MAX);
+        regs.imsc.set(imsc);
+    }
+
+    /// Drop what is queued in the TX FIFO, the character on the wire still completes.
+    ///
+    /// The PL011 can only flush both FIFOs at once, so received bytes are moved
+    /// to the software buffer first. In polled mode they are lost.
+    pub fn flush_tx(&mut self) {
+        // the interrupt handler is the only producer of `rx_ring`, keep it out
+        let imsc = self.regs().imsc.get();
+        self.regs().imsc.set(0);
+        let cr_l = self.regs().cr_l.get();
+        self.regs().cr_l.modify(CONTROLL::ENABLE::CLEAR);
+        if self.rx_ring.capacity() > 0 {
+            self.drain_rx();
+        }
+        let regs = self.regs();
+        let cr_h = regs.cr_h.get();
+        regs.cr_h.modify(CONTROLH::FEN::CLEAR);
+        regs.cr_h.set(cr_h);
+        regs.cr_l.set(cr_l);
+        regs.imsc.set(imsc);
+    }
+
+    /// Let the transmitter drain, then mask all interrupts and disable the UART,
+    /// so the last characters are not cut off on reboot.
+    ///
+    /// The port is disabled even if the transmitter does not go idle in time,
+    /// the error then tells that output may have been truncated.
+    pub fn shutdown(&mut self) -> Result<(), UartError> {
+        let drained = self.wait_tx_idle();
+        let regs = self.regs();
+        regs.imsc.set(0);
+        regs.icr.set(0x7ff);
+        regs.cr_l.write(CONTROLL::ENABLE::CLEAR);
+        self.standby = None;
+        Ok(drained?)
+    }
+
+    /// Shut the transmitter down and keep only the receiver interrupts armed,
+    /// so console activity can wake a sleeping system.
+    pub fn enter_standby(&mut self) -> Result<(), UartError> {
+        if self.standby.is_some() {
+            return Ok(());
+        }
+        // let the last character leave before the transmitter goes down
+        self.wait_tx_idle()?;
+        self.standby = Some((self.regs().imsc.get(), self.regs().cr_l.get()));
+        self.woken = false;
+        let regs = self.regs();
+        // RXIM alone would wait for the FIFO level, RTIM fires on a single byte
+        regs.imsc.write(INTERRUPT::RXIM::SET + INTERRUPT::RTIM::SET);
+        regs.cr_l.write(CONTROLL::ENABLE::SET + CONTROLL::RXE::SET);
+        Ok(())
+    }
+
+    /// Restore the configuration saved by [`Self::enter_standby`].
+    /// Returns whether RX activity was seen while in standby, the received
+    /// bytes are kept for [`Self::read_bytes`].
+    pub fn exit_standby(&mut self) -> bool {
+        if let Some((imsc, cr_l)) = self.standby.take() {
+            let regs = self.regs();
+            regs.cr_l.set(cr_l);
+            regs.imsc.set(imsc);
+        }
+        core::mem::take(&mut self.woken)
+    }
+
+    /// Detect the peer baud rate and leave the port configured for it.
+    ///
+    /// The peer has to send a continuous stream of `0x55` ('U') in 8N1. The port
+    /// is switched to polled 8N1 meanwhile and every rate of
+    /// [`STANDARD_BAUD_RATES`] `clock_hz` can divide down to is listened to for
+    /// `window`; a rate is taken once enough characters arrive without errors
+    /// and their measured spacing agrees with it within 5%.
+    ///
+    /// Afterwards the port goes back to its previous line settings and irq or
+    /// polled mode, at the detected rate, or at the previous one if nothing
+    /// matched. A port that was never initialised is left disabled then.
+    pub fn detect_baud(&mut self, clock_hz: u32, window: Duration) -> Option<u32> {
+        let (prev_clock, prev_baud, irq) = (self.clock_hz, self.baud_rate, self.irq_mode);
+        let prev_line = core::mem::replace(&mut self.line, LineConfig::DEFAULT);
+        let detected = STANDARD_BAUD_RATES
+            .into_iter()
+            .filter(|&baud| Self::divisors_valid(clock_hz, baud))
+            .find(|&baud| {
+                self.init_no_irq(clock_hz, baud);
+                self.measure_baud(window)
+                    .is_some_and(|measured| measured.abs_diff(baud) * 20 <= baud)
+            });
+        self.line = prev_line;
+        let (clock_hz, baud) = match detected {
+            Some(baud) => (clock_hz, baud),
+            None if prev_baud != 0 => (prev_clock, prev_baud),
+            None => {
+                self.regs().cr_l.write(CONTROLL::ENABLE::CLEAR);
+                self.baud_rate = 0;
+                return None;
+            }
+        };
+        if irq {
+            self.init_irq(clock_hz, baud);
+        } else {
+            self.init_no_irq(clock_hz, baud);
+        }
+        detected
+    }
+
+    /// Measure the rate of back to back [`AUTOBAUD_CHAR`]s, 8N1 framing.
+    fn measure_baud(&self, window: Duration) -> Option<u32> {
+        let regs = self.regs();
+        let deadline = kernel::now() + window;
+        // drop whatever was received at the previous rate
+        while !regs.fr.is_set(FLAG::RXFE) {
+            regs.dr.get();
+        }
+        let mut first = Duration::ZERO;
+        let mut n = 0;
+        let mut wdt = Watchdog::new();
+        loop {
+            wdt.poll();
+            let now = kernel::now();
+            if now > deadline {
+                return None;
+            }
+            if regs.fr.is_set(FLAG::RXFE) {
+                continue;
+            }
+            let data = regs.dr.extract();
+            let broken = data.is_set(DATA::FE)
+                || data.is_set(DATA::PE)
+                || data.is_set(DATA::BE)
+                || data.is_set(DATA::OE);
+            if broken || data.read(DATA::RAW) != AUTOBAUD_CHAR {
+                // not locked on the character boundary yet, start over
+                n = 0;
+                continue;
+            }
+            if n == 0 {
+                first = now;
+            }
+            n += 1;
+            if n == AUTOBAUD_SAMPLES {
+                let bits = LineConfig::DEFAULT.frame_bits() * (AUTOBAUD_SAMPLES - 1);
+                return Some(units::baud_from(bits, now - first));
+            }
+        }
+    }
+
     const fn regs(&self) -> &PhytiumUartRegs {
         unsafe { self.base.as_ref() }
     }
 
-    pub fn read_byte_poll(&self) -> u8 {
-        while self.regs().fr.read(FLAG::RXFE) != 0 {}
-        (self.regs().dr.get() & 0xff) as u8
+    /// Wait for the next byte, for as long as the peer stays silent.
+    pub fn read_byte_poll(&mut self) -> u8 {
+        loop {
+            while self.regs().fr.read(FLAG::RXFE) != 0 {}
+            let data = self.regs().dr.extract();
+            if let Some(b) = self.filter_sysrq(data) {
+                return b;
+            }
+        }
     }
 
-    pub fn put_byte_poll(&mut self, b: u8) {
-        while self.regs().fr.read(FLAG::TXFF) == 1 {}
-        self.regs().dr.set(b as u32);
+    /// Install or remove the emergency command hook.
+    pub fn set_sysrq(&mut self, hook: Option<SysrqHook>) {
+        if let Some(SysrqHook {
+            trigger: SysrqTrigger::Sequence(seq),
+            ..
+        }) = &hook
+        {
+            assert!(!seq.is_empty(), "empty sysrq sequence");
+        }
+        self.sysrq = hook;
+        self.sysrq_matched = 0;
+    }
+
+    /// Feed a received character through the sysrq state machine,
+    /// returns the byte to deliver if any.
+    fn filter_sysrq(&mut self, data: LocalRegisterCopy<u32, DATA::Register>) -> Option<u8> {
+        let b = data.read(DATA::RAW) as u8;
+        let Some(hook) = self.sysrq else {
+            return Some(b);
+        };
+        let armed = match hook.trigger {
+            SysrqTrigger::Break => 1,
+            SysrqTrigger::Sequence(seq) => seq.len(),
+        };
+        if self.sysrq_matched == armed {
+            self.sysrq_matched = 0;
+            (hook.handler)(b);
+            return None;
+        }
+        match hook.trigger {
+            SysrqTrigger::Break => {
+                if data.is_set(DATA::BE) {
+                    self.sysrq_matched = 1;
+                    return None;
+                }
+            }
+            SysrqTrigger::Sequence(seq) => {
+                self.sysrq_matched = if seq[self.sysrq_matched] == b {
+                    self.sysrq_matched + 1
+                } else {
+                    usize::from(seq[0] == b)
+                };
+            }
+        }
+        Some(b)
+    }
+
+    /// Whether firmware left the port enabled for sending, with a baud rate set.
+    pub fn probe_enabled(&self) -> bool {
+        let regs = self.regs();
+        regs.cr_l.is_set(CONTROLL::ENABLE)
+            && regs.cr_l.is_set(CONTROLL::TXE)
+            && (regs.tibd.get() != 0 || regs.tfbd.get() != 0)
+    }
+
+    /// Emit `b` with whatever settings firmware left, for very early boot
+    /// before `init_*` and before the [`Kernel`](crate::kernel::Kernel) is usable.
+    ///
+    /// Nothing is checked, call [`Self::probe_enabled`] first. The byte is
+    /// dropped if the FIFO does not drain within a bounded number of polls.
+    pub fn write_byte_raw_unchecked(&self, b: u8) {
+        let regs = self.regs();
+        for _ in 0..RAW_WRITE_SPINS {
+            if !regs.fr.is_set(FLAG::TXFF) {
+                regs.dr.set(b as u32);
+                return;
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Send `b`, waiting for room in the TX FIFO within the [`PollPolicy`].
+    pub fn put_byte_poll(&mut self, b: u8) -> Result<(), UartError> {
+        self.rs485_begin();
+        let room = self.poll.wait(|| !self.regs().fr.is_set(FLAG::TXFF));
+        if room.is_ok() {
+            self.regs().dr.set(b as u32);
+        }
+        self.rs485_end();
+        Ok(room?)
+    }
+
+    /// Enable RS485 mode, DE is asserted around every transmission.
+    /// `None` goes back to plain full-duplex operation.
+    pub fn set_rs485(&mut self, cfg: Option<Rs485Config>) {
+        if let Some(cfg) = &cfg {
+            (cfg.set_de)(false);
+        }
+        self.rs485 = cfg;
+    }
+
+    fn rs485_begin(&self) {
+        if let Some(cfg) = &self.rs485 {
+            (cfg.set_de)(true);
+            kernel::sleep(cfg.delay_before_send);
+        }
+    }
+
+    fn rs485_end(&self) {
+        if let Some(cfg) = &self.rs485 {
+            // the last character is still in the shifter until BUSY clears
+            if self.wait_tx_idle().is_err() {
+                log::warn!("uart: transmitter stuck busy, releasing DE anyway");
+            }
+            kernel::sleep(cfg.delay_after_send);
+            (cfg.set_de)(false);
+        }
+    }
+
+    /// Whether an unmasked interrupt is pending, to demux shared lines.
+    pub fn irq_pending(&self) -> bool {
+        self.regs().mis.get() != 0
     }
 
     pub fn handle_interrupt(&mut self) {
         // self.irq_cnt += 1;
-        if self.regs().fr.is_set(FLAG::TXFE) {
+        if self.standby.is_some() {
+            let mis = self.regs().mis.extract();
+            if mis.is_set(INTERRUPT::RXIM) || mis.is_set(INTERRUPT::RTIM) {
+                self.woken = true;
+            }
+        }
+        let mis = self.regs().mis.extract();
+        // fires when the TX FIFO falls to its trigger level, not only once empty
+        if mis.is_set(INTERRUPT::TXIM) {
             self.tx_irq_cnt += 1;
             self.waker.wake();
         }
         if self.regs().fr.is_set(FLAG::RXFF) {
             self.rx_irq_cnt += 1;
         }
-        self.regs()
-            .icr
-            .write(INTERRUPT::TXIM::SET + INTERRUPT::RXIM::SET);
+        if mis.is_set(INTERRUPT::RXIM) || mis.is_set(INTERRUPT::RTIM) {
+            self.drain_rx();
+        }
+        if mis.is_set(INTERRUPT::RTIM) {
+            self.rx_idle_events.fetch_add(1, Ordering::Release);
+            self.idle_waker.wake();
+        }
+        if mis.is_set(INTERRUPT::OEIM) {
+            // the FIFO still holds the bytes before the lost one, keep them
+            // and carry on with a clean error state
+            self.rx_overruns += 1;
+            self.drain_rx();
+            self.regs().rsr.set(0);
+        }
+        self.regs().icr.write(
+            INTERRUPT::TXIM::SET
+                + INTERRUPT::RXIM::SET
+                + INTERRUPT::RTIM::SET
+                + INTERRUPT::OEIM::SET,
+        );
+    }
+
+    fn drain_rx(&mut self) {
+        while !self.regs().fr.is_set(FLAG::RXFE) {
+            let data = self.regs().dr.extract();
+            let Some(b) = self.filter_sysrq(data) else {
+                continue;
+            };
+            if self.rx_ring.is_full() {
+                self.rx_dropped += 1;
+                continue;
+            }
+            if self.rx_timestamps {
+                self.rx_stamps.push(kernel::now().as_nanos() as u64);
+            }
+            self.rx_ring.push(b);
+        }
+        self.rx_waker.wake();
+    }
+
+    /// Read at least one byte received by the interrupt handler.
+    pub fn read_bytes<'a>(&'a mut self, buf: &'a mut [u8]) -> impl Future<Output = usize> + 'a {
+        ReadFuture {
+            uart: self,
+            buf,
+            stamps: None,
+        }
+    }
+
+    /// Like [`Self::read_bytes`], also filling `stamps` with the arrival time of
+    /// each byte in nanoseconds since boot, see [`Self::set_rx_timestamps`].
+    /// Reads at most `stamps.len()` bytes.
+    pub fn read_bytes_timestamped<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+        stamps: &'a mut [u64],
+    ) -> impl Future<Output = usize> + 'a {
+        let n = buf.len().min(stamps.len());
+        ReadFuture {
+            uart: self,
+            buf: &mut buf[..n],
+            stamps: Some(stamps),
+        }
     }
 
+    /// Resolves on the next idle line event: bytes were received, then the line
+    /// stayed quiet for [`RX_IDLE_BITS`] bit periods. Idle delimited protocols
+    /// such as Modbus RTU can take it as end of frame, interrupt mode only.
+    ///
+    /// The event comes from the receive timeout interrupt, which needs bytes
+    /// left in the FIFO, so a frame ending exactly on the RX trigger level is
+    /// not reported.
+    pub fn wait_rx_idle(&self) -> impl Future<Output = ()> + '_ {
+        IdleFuture {
+            uart: self,
+            seen: self.rx_idle_events.load(Ordering::Acquire),
+        }
+    }
+
+    /// Resolves once every byte is in the TX FIFO, some may still be on the wire.
     pub fn write_bytes<'a>(&'a mut self, b: &'a [u8]) -> impl Future<Output = usize> + 'a {
         WriteFuture {
             uart: self,
             bytes: b,
             n: 0,
+            de_asserted: false,
+            complete: false,
+        }
+    }
+
+    /// Like [`Self::write_bytes`], but resolves only once the last stop bit has
+    /// left the shifter and the line is idle, e.g. before changing the baud rate.
+    pub fn write_bytes_complete<'a>(&'a mut self, b: &'a [u8]) -> impl Future<Output = usize> + 'a {
+        WriteFuture {
+            uart: self,
+            bytes: b,
+            n: 0,
+            de_asserted: false,
+            complete: true,
         }
     }
 }
@@ -207,6 +1240,9 @@ pub struct WriteFuture<'a> {
     uart: &'a PhytiumUart,
     bytes: &'a [u8],
     n: usize,
+    de_asserted: bool,
+    /// wait for BUSY to clear too
+    complete: bool,
 }
 
 impl<'a> Future for WriteFuture<'a> {
@@ -216,8 +1252,22 @@ impl<'a> Future for WriteFuture<'a> {
         cx: &mut core::task::Context<'_>,
     ) -> core::task::Poll<Self::Output> {
         let this = self.get_mut();
+        if !this.de_asserted {
+            this.uart.rs485_begin();
+            this.de_asserted = true;
+        }
         loop {
             if this.n >= this.bytes.len() {
+                // DE must stay asserted until the shifter is empty as well
+                if (this.complete || this.uart.rs485.is_some())
+                    && this.uart.regs().fr.is_set(FLAG::BUSY)
+                {
+                    // no interrupt for the shifter draining, poll again
+                    cx.waker().wake_by_ref();
+                    return core::task::Poll::Pending;
+                }
+                this.uart.rs485_end();
+                this.de_asserted = false;
                 return core::task::Poll::Ready(this.n);
             }
             if this.uart.regs().fr.is_set(FLAG::TXFF) {
@@ -232,6 +1282,82 @@ impl<'a> Future for WriteFuture<'a> {
     }
 }
 
+pub struct ReadFuture<'a> {
+    uart: &'a PhytiumUart,
+    buf: &'a mut [u8],
+    stamps: Option<&'a mut [u64]>,
+}
+
+impl ReadFuture<'_> {
+    fn pop(&mut self) -> usize {
+        let n = self.uart.rx_ring.pop_slice(self.buf);
+        if self.uart.rx_timestamps {
+            match &mut self.stamps {
+                Some(stamps) => {
+                    self.uart.rx_stamps.pop_slice(&mut stamps[..n]);
+                }
+                None => self.uart.rx_stamps.skip(n),
+            }
+        }
+        n
+    }
+}
+
+impl<'a> Future for ReadFuture<'a> {
+    type Output = usize;
+    fn poll(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        if this.buf.is_empty() {
+            return core::task::Poll::Ready(0);
+        }
+        let n = this.pop();
+        if n > 0 {
+            return core::task::Poll::Ready(n);
+        }
+        this.uart.rx_waker.register(cx.waker());
+        // the interrupt may have pushed before the waker was registered
+        match this.pop() {
+            0 => core::task::Poll::Pending,
+            n => core::task::Poll::Ready(n),
+        }
+    }
+}
+
+pub struct IdleFuture<'a> {
+    uart: &'a PhytiumUart,
+    seen: usize,
+}
+
+impl<'a> Future for IdleFuture<'a> {
+    type Output = ();
+    fn poll(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Self::Output> {
+        let events = &self.uart.rx_idle_events;
+        if events.load(Ordering::Acquire) != self.seen {
+            return core::task::Poll::Ready(());
+        }
+        self.uart.idle_waker.register(cx.waker());
+        if events.load(Ordering::Acquire) != self.seen {
+            return core::task::Poll::Ready(());
+        }
+        core::task::Poll::Pending
+    }
+}
+
+impl Drop for WriteFuture<'_> {
+    fn drop(&mut self) {
+        // never leave the bus driven if the write is cancelled
+        if self.de_asserted {
+            self.uart.rs485_end();
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -250,4 +1376,30 @@ mod test {
         );
         assert_eq!((54, 16), PhytiumUart::get_ti_tf(clock, bd_rate));
     }
+
+    #[test]
+    fn test_divisors_valid() {
+        assert!(PhytiumUart::divisors_valid(100_000_000, 115200));
+        assert!(!PhytiumUart::divisors_valid(100_000_000, 0));
+        assert!(!PhytiumUart::divisors_valid(0, 115200));
+        assert!(!PhytiumUart::divisors_valid(100_000_000, u32::MAX));
+        assert!(!PhytiumUart::divisors_valid(u32::MAX, 1));
+    }
+
+    #[test]
+    fn test_parity_bit() {
+        let mut line = LineConfig {
+            parity: Parity::Even,
+            ..LineConfig::DEFAULT
+        };
+        assert_eq!(line.parity_bit(0b0000_0011), Some(false));
+        assert_eq!(line.parity_bit(0b0000_0111), Some(true));
+        line.parity = Parity::Odd;
+        assert_eq!(line.parity_bit(0b0000_0111), Some(false));
+        // bit 7 is not sent with 7 data bits
+        line.data_bits = DataBits::Seven;
+        assert_eq!(line.parity_bit(0b1000_0000), Some(true));
+        line.parity = Parity::None;
+        assert_eq!(line.parity_bit(0x55), None);
+    }
 }
@@ -0,0 +1,127 @@
+use alloc::vec::Vec;
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Single producer (interrupt handler), single consumer (reader) ring.
+pub(crate) struct SpscRing<T> {
+    slots: Vec<UnsafeCell<T>>,
+    /// next slot written by the producer
+    head: AtomicUsize,
+    /// next slot read by the consumer
+    tail: AtomicUsize,
+}
+
+impl<T: Copy + Default> SpscRing<T> {
+    pub const fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        // one slot stays empty to tell full from empty
+        let mut slots = Vec::with_capacity(capacity + 1);
+        slots.resize_with(capacity + 1, || UnsafeCell::new(T::default()));
+        Self {
+            slots,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len().saturating_sub(1)
+    }
+
+    /// Only stable on the producer side, the consumer can only make room.
+    pub fn is_full(&self) -> bool {
+        let head = self.head.load(Ordering::Relaxed);
+        self.slots.is_empty() || (head + 1) % self.slots.len() == self.tail.load(Ordering::Acquire)
+    }
+
+    /// Producer side, returns `false` if the ring is full.
+    pub fn push(&self, v: T) -> bool {
+        if self.slots.is_empty() {
+            return false;
+        }
+        let head = self.head.load(Ordering::Relaxed);
+        let next = (head + 1) % self.slots.len();
+        if next == self.tail.load(Ordering::Acquire) {
+            return false;
+        }
+        unsafe { *self.slots[head].get() = v };
+        self.head.store(next, Ordering::Release);
+        true
+    }
+
+    /// Consumer side, moves as many items as fit into `out`.
+    pub fn pop_slice(&self, out: &mut [T]) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let mut tail = self.tail.load(Ordering::Relaxed);
+        let mut n = 0;
+        while n < out.len() && tail != head {
+            out[n] = unsafe { *self.slots[tail].get() };
+            tail = (tail + 1) % self.slots.len();
+            n += 1;
+        }
+        self.tail.store(tail, Ordering::Release);
+        n
+    }
+
+    /// Consumer side, drops up to `n` items.
+    pub fn skip(&self, n: usize) {
+        let head = self.head.load(Ordering::Acquire);
+        let mut tail = self.tail.load(Ordering::Relaxed);
+        for _ in 0..n {
+            if tail == head {
+                break;
+            }
+            tail = (tail + 1) % self.slots.len();
+        }
+        self.tail.store(tail, Ordering::Release);
+    }
+}
+
+impl<T> fmt::Debug for SpscRing<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpscRing")
+            .field("slots", &self.slots.len())
+            .field("head", &self.head)
+            .field("tail", &self.tail)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_wrap() {
+        let ring = SpscRing::<u8>::with_capacity(3);
+        let mut out = [0u8; 4];
+        for round in 0..4u8 {
+            assert!(ring.push(round));
+            assert!(ring.push(round + 1));
+            assert_eq!(ring.pop_slice(&mut out[..1]), 1);
+            assert_eq!(out[0], round);
+            assert!(ring.push(round + 2));
+            assert!(ring.push(round + 3));
+            assert!(!ring.push(round + 4));
+            assert_eq!(ring.pop_slice(&mut out), 3);
+            assert_eq!(&out[..3], &[round + 1, round + 2, round + 3]);
+        }
+        assert!(!SpscRing::<u8>::new().push(0));
+        assert!(SpscRing::<u8>::new().is_full());
+
+        assert!(ring.push(1) && ring.push(2) && ring.push(3));
+        assert!(ring.is_full());
+        ring.skip(2);
+        assert_eq!(ring.pop_slice(&mut out), 1);
+        assert_eq!(out[0], 3);
+    }
+}
@@ -0,0 +1,183 @@
+//! Pattern generators and a loopback soak test for exercising a port on
+//! real hardware, e.g. from `bare_test`.
+
+use core::time::Duration;
+
+use crate::{
+    kernel,
+    misc::{timer, watchdog::Watchdog},
+    uart::pl011::PhytiumUart,
+};
+
+/// How long a soak chunk may take to come back before its bytes count as lost.
+pub const SOAK_RX_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// 0, 1, .., 255, 0, ..
+    Ramp,
+    /// PRBS-15 (x^15 + x^14 + 1), MSB first.
+    Prbs15,
+}
+
+/// Endless byte stream of a [`Pattern`].
+#[derive(Debug, Clone)]
+pub struct PatternGen {
+    pattern: Pattern,
+    state: u16,
+}
+
+impl PatternGen {
+    pub const fn new(pattern: Pattern) -> Self {
+        Self {
+            pattern,
+            // any non-zero seed works for the LFSR
+            state: match pattern {
+                Pattern::Ramp => 0,
+                Pattern::Prbs15 => 0x7fff,
+            },
+        }
+    }
+
+    pub fn next_byte(&mut self) -> u8 {
+        match self.pattern {
+            Pattern::Ramp => {
+                let b = self.state as u8;
+                self.state = b.wrapping_add(1) as u16;
+                b
+            }
+            Pattern::Prbs15 => {
+                for _ in 0..8 {
+                    let bit = ((self.state >> 14) ^ (self.state >> 13)) & 1;
+                    self.state = ((self.state << 1) | bit) & 0x7fff;
+                }
+                self.state as u8
+            }
+        }
+    }
+
+    pub fn fill(&mut self, buf: &mut [u8]) {
+        buf.iter_mut().for_each(|b| *b = self.next_byte());
+    }
+
+    /// Continue the stream after `prev` then `last` were received.
+    fn resync(&mut self, prev: u8, last: u8) {
+        self.state = match self.pattern {
+            Pattern::Ramp => last.wrapping_add(1) as u16,
+            Pattern::Prbs15 => ((prev as u16) << 8 | last as u16) & 0x7fff,
+        };
+    }
+}
+
+/// Error counters of a [`PatternCheck`] or [`soak`] run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SoakStats {
+    pub sent: u64,
+    pub received: u64,
+    /// Received bytes not matching the pattern.
+    pub errors: u64,
+    /// Bytes that did not come back within [`SOAK_RX_TIMEOUT`].
+    pub lost: u64,
+}
+
+/// Checks received bytes against a [`Pattern`], resynchronising after an
+/// error so a dropped byte costs one or two errors instead of the whole run.
+#[derive(Debug, Clone)]
+pub struct PatternCheck {
+    expected: PatternGen,
+    prev: u8,
+    stats: SoakStats,
+}
+
+impl PatternCheck {
+    pub const fn new(pattern: Pattern) -> Self {
+        Self {
+            expected: PatternGen::new(pattern),
+            prev: 0,
+            stats: SoakStats {
+                sent: 0,
+                received: 0,
+                errors: 0,
+                lost: 0,
+            },
+        }
+    }
+
+    pub fn check(&mut self, data: &[u8]) {
+        for &b in data {
+            if self.expected.next_byte() != b {
+                self.stats.errors += 1;
+                self.expected.resync(self.prev, b);
+            }
+            self.prev = b;
+        }
+        self.stats.received += data.len() as u64;
+    }
+
+    pub fn stats(&self) -> SoakStats {
+        self.stats
+    }
+}
+
+/// Send `pattern` through the internal loopback for `duration` and verify
+/// what comes back.
+///
+/// The port must be initialised with `init_irq` and its interrupt hooked up.
+/// Data goes out one FIFO worth at a time, each chunk read back before the
+/// next is sent. Loopback is switched off again before returning.
+pub async fn soak(uart: &mut PhytiumUart, pattern: Pattern, duration: Duration) -> SoakStats {
+    let mut chunk = [0u8; 32];
+    let chunk_len = uart.fifo_depth().min(chunk.len());
+    let mut tx = PatternGen::new(pattern);
+    let mut rx = PatternCheck::new(pattern);
+    let mut sent = 0;
+    let mut lost = 0;
+
+    uart.set_loopback(true);
+    let mut wdt = Watchdog::new();
+    let deadline = kernel::now() + duration;
+    while kernel::now() < deadline {
+        let chunk = &mut chunk[..chunk_len];
+        tx.fill(chunk);
+        sent += uart.write_bytes_complete(chunk).await as u64;
+
+        let mut got = 0;
+        while got < chunk.len() {
+            match timer::timeout(SOAK_RX_TIMEOUT, uart.read_bytes(&mut chunk[got..])).await {
+                Ok(n) => got += n,
+                Err(_) => break,
+            }
+        }
+        rx.check(&chunk[..got]);
+        lost += (chunk.len() - got) as u64;
+        wdt.poll();
+    }
+    uart.set_loopback(false);
+
+    SoakStats {
+        sent,
+        lost,
+        ..rx.stats()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_pattern_resync() {
+        for (pattern, errors) in [(Pattern::Ramp, 1), (Pattern::Prbs15, 2)] {
+            let mut data = [0u8; 600];
+            PatternGen::new(pattern).fill(&mut data);
+            let mut check = PatternCheck::new(pattern);
+            check.check(&data);
+            assert_eq!(check.stats().errors, 0);
+
+            let mut check = PatternCheck::new(pattern);
+            check.check(&data[..100]);
+            check.check(&data[101..]);
+            assert_eq!(check.stats().errors, errors);
+            assert_eq!(check.stats().received, 599);
+        }
+    }
+}